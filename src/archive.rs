@@ -0,0 +1,153 @@
+use crate::error::ReasonError;
+use crate::Result;
+
+use std::fs;
+use std::path::Path;
+
+/// Prefix of the errors of malformed archives
+const ERROR_PREFIX: &str = "Bad archive";
+
+/// Maximum size of a packed archive, so the resulting chunk stays reasonable
+pub const MAX_ARCHIVE_SIZE: usize = 16 * 1024 * 1024;
+
+/// Packs named files into a single buffer
+/// Each entry is stored as (name length: u16, name, data length: u32, data), all lengths being big endian
+pub fn pack(entries: &[(String, Vec<u8>)]) -> Result<Vec<u8>> {
+    let mut archive = vec![];
+    for (name, data) in entries {
+        check_name(name)?;
+        let name_length = u16::try_from(name.len())
+            .map_err(|_| ReasonError::boxed(ERROR_PREFIX, format!("File name \"{name}\" is too long")))?;
+        let data_length = u32::try_from(data.len())
+            .map_err(|_| ReasonError::boxed(ERROR_PREFIX, format!("File \"{name}\" is too large")))?;
+
+        archive.extend(name_length.to_be_bytes());
+        archive.extend(name.as_bytes());
+        archive.extend(data_length.to_be_bytes());
+        archive.extend(data);
+
+        if archive.len() > MAX_ARCHIVE_SIZE {
+            return Err(ReasonError::boxed(ERROR_PREFIX, format!("Archive exceeds the {MAX_ARCHIVE_SIZE} bytes limit")));
+        }
+    }
+    Ok(archive)
+}
+
+/// Unpacks a buffer created by `pack` into its named files
+pub fn unpack(mut archive: &[u8]) -> Result<Vec<(String, Vec<u8>)>> {
+    if archive.len() > MAX_ARCHIVE_SIZE {
+        return Err(ReasonError::boxed(ERROR_PREFIX, format!("Archive exceeds the {MAX_ARCHIVE_SIZE} bytes limit")));
+    }
+
+    let mut entries = vec![];
+    while !archive.is_empty() {
+        let name_length = u16::from_be_bytes(take(&mut archive, 2)?.try_into()?) as usize;
+        let name = String::from_utf8(take(&mut archive, name_length)?.to_vec())?;
+        check_name(&name)?;
+        let data_length = u32::from_be_bytes(take(&mut archive, 4)?.try_into()?) as usize;
+        let data = take(&mut archive, data_length)?.to_vec();
+        entries.push((name, data));
+    }
+    Ok(entries)
+}
+
+/// Reads the given files and packs them, keeping only their file names
+pub fn pack_files(paths: &[String]) -> Result<Vec<u8>> {
+    let mut entries = vec![];
+    for path in paths {
+        let name = Path::new(path)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| ReasonError::boxed(ERROR_PREFIX, format!("Can't get the file name of \"{path}\"")))?;
+        entries.push((name.to_string(), fs::read(path)?));
+    }
+    pack(&entries)
+}
+
+/// Unpacks the archive into the given directory, returning the names of the extracted files
+pub fn extract_all(archive: &[u8], directory: &Path) -> Result<Vec<String>> {
+    fs::create_dir_all(directory)?;
+    let mut names = vec![];
+    for (name, data) in unpack(archive)? {
+        fs::write(directory.join(&name), data)?;
+        names.push(name);
+    }
+    Ok(names)
+}
+
+/// Rejects names that could escape the extraction directory
+fn check_name(name: &str) -> Result<()> {
+    let is_plain_file_name = Path::new(name).file_name().and_then(|file_name| file_name.to_str()) == Some(name);
+    if name.is_empty() || !is_plain_file_name || name.contains(['/', '\\']) {
+        Err(ReasonError::boxed(ERROR_PREFIX, format!("Invalid file name \"{name}\" in archive")))
+    } else {
+        Ok(())
+    }
+}
+
+/// Splits the first `length` bytes off the buffer
+fn take<'a>(buffer: &mut &'a [u8], length: usize) -> Result<&'a [u8]> {
+    if buffer.len() < length {
+        return Err(ReasonError::boxed(ERROR_PREFIX, "Archive is truncated".to_string()));
+    }
+    let (head, tail) = buffer.split_at(length);
+    *buffer = tail;
+    Ok(head)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pack_unpack() {
+        let entries = vec![
+            ("notes.txt".to_string(), b"Some notes".to_vec()),
+            ("blob.bin".to_string(), vec![0, 159, 146, 150, 255]),
+        ];
+        let archive = pack(&entries).unwrap();
+        assert_eq!(unpack(&archive).unwrap(), entries);
+    }
+
+    #[test]
+    fn test_files_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let first = source.path().join("first.txt");
+        let second = source.path().join("second.bin");
+        fs::write(&first, b"first file").unwrap();
+        fs::write(&second, [1, 2, 3, 0, 255]).unwrap();
+
+        let paths = [first.to_str().unwrap().to_string(), second.to_str().unwrap().to_string()];
+        let archive = pack_files(&paths).unwrap();
+
+        let destination = tempfile::tempdir().unwrap();
+        let names = extract_all(&archive, destination.path()).unwrap();
+        assert_eq!(names, vec!["first.txt", "second.bin"]);
+        assert_eq!(fs::read(destination.path().join("first.txt")).unwrap(), b"first file");
+        assert_eq!(fs::read(destination.path().join("second.bin")).unwrap(), [1, 2, 3, 0, 255]);
+    }
+
+    #[test]
+    fn test_path_traversal_rejected() {
+        for name in ["../evil", "/etc/passwd", "dir/file", "..", "dir\\file", ""] {
+            assert!(pack(&[(name.to_string(), vec![])]).is_err(), "{name} should be rejected");
+        }
+
+        let mut archive = vec![0, 6];
+        archive.extend(b"../bad");
+        archive.extend([0, 0, 0, 0]);
+        assert!(unpack(&archive).is_err());
+    }
+
+    #[test]
+    fn test_truncated_archive() {
+        let archive = pack(&[("file".to_string(), b"data".to_vec())]).unwrap();
+        assert!(unpack(&archive[..archive.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let entries = vec![("big".to_string(), vec![0; MAX_ARCHIVE_SIZE])];
+        assert!(pack(&entries).is_err());
+    }
+}
@@ -1,46 +1,457 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use pngme::Png;
 
-use std::path::PathBuf;
+use std::num::NonZeroUsize;
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct EncodeArgs {
+    /// path to the PNG file, or a pattern like "*.png" with --glob
+    pub filepath: String, 
+
+    /// encode every PNG file matching the filepath pattern, reporting the failures at the end
+    #[arg(long, conflicts_with = "stdin")]
+    pub glob: bool,
+
+    /// 4-letter chunk type
+    #[arg(required_unless_present_any = ["auto_type", "exif_file", "text"])]
+    pub chunk_type: Option<String>, 
+
+    /// message to add to the png file, or - to read it from stdin
+    #[arg(required_unless_present_any = ["embed_files", "stdin", "message_file", "auto_type", "exif_file", "text"])]
+    pub message: Option<String>,
+
+    /// decode the message from base64 to store raw bytes
+    #[arg(long, conflicts_with_all = ["embed_files", "message_file"])]
+    pub base64: bool,
+
+    /// decode the message from hex to store raw bytes
+    #[arg(long, conflicts_with_all = ["base64", "embed_files", "message_file"])]
+    pub hex: bool,
+
+    /// pick a private, ancillary, safe to copy chunk type that isn't used in the file yet (the message then follows the path)
+    #[arg(long, conflicts_with = "stdin")]
+    pub auto_type: bool,
+
+    /// files to pack together into the chunk instead of a message
+    #[arg(long, num_args = 1.., conflicts_with = "message")]
+    pub embed_files: Vec<String>,
+
+    /// file to read the message from instead of the command line
+    #[arg(long, conflicts_with_all = ["message", "embed_files"])]
+    pub message_file: Option<String>,
+
+    /// offset of the first byte of the message file to read
+    #[arg(long, default_value_t = 0, requires = "message_file")]
+    pub message_offset: u64,
+
+    /// number of bytes of the message file to read (defaults to the rest of the file)
+    #[arg(long, requires = "message_file")]
+    pub message_len: Option<u64>,
+
+    /// EXIF (TIFF) file to store in the eXIf chunk instead of a message
+    #[arg(long, conflicts_with_all = [
+        "chunk_type", "message", "embed_files", "message_file", "auto_type", "chain", "stdin", "password", "password_prompt",
+    ])]
+    pub exif_file: Option<String>,
+
+    /// keyword=value to store in a standard tEXt chunk instead of a message
+    #[arg(long, conflicts_with_all = [
+        "chunk_type", "message", "embed_files", "message_file", "auto_type", "exif_file", "chain", "stdin",
+        "compress", "chunk_size", "password", "password_prompt",
+    ])]
+    pub text: Option<String>,
+
+    /// leave the file as is if it already has a chunk of this type
+    #[arg(long, conflicts_with = "stdin")]
+    pub if_missing: bool,
+
+    /// reject chunk types that aren't safe to copy
+    #[arg(long)]
+    pub require_safe_to_copy: bool,
+
+    /// write the result to a new temporary file and print its path instead of modifying the PNG file
+    #[arg(long)]
+    pub to_temp: bool,
+
+    /// insert the chunk at this index instead of appending it
+    #[arg(long, conflicts_with_all = ["exif_file", "chain", "stdin"])]
+    pub before: Option<usize>,
+
+    /// split messages longer than this number of bytes into several chunks of the same type, read them back with decode --join
+    #[arg(long, conflicts_with_all = ["exif_file", "chain", "stdin"])]
+    pub chunk_size: Option<NonZeroUsize>,
+
+    /// prefix the data with the hash of the previous chunk of the same type, so that decode can detect reordering
+    #[arg(long)]
+    pub chain: bool,
+
+    /// compress the message with zlib, decode it with --decompress
+    #[arg(long, conflicts_with = "exif_file")]
+    pub compress: bool,
+
+    #[command(flatten)]
+    pub password: PasswordArgs,
+
+    /// read the message from stdin and stream the PNG file to the output instead of loading it in memory
+    #[arg(long, conflicts_with_all = ["message", "embed_files", "message_file", "chain"])]
+    pub stdin: bool,
+
+    // /// output file
+    // out: Option<String>
+}
+
+#[derive(clap::Args, Debug)]
+pub struct DecodeArgs {
+    /// path to the PNG file 
+    pub filepath: String, 
+
+    /// 4-letter chunk type
+    #[arg(required_unless_present = "text")]
+    pub chunk_type: Option<String>,
+
+    /// print the keyword=value pairs of the standard tEXt chunks
+    #[arg(long, conflicts_with_all = ["chunk_type", "extract_all", "verify_chain", "join", "decompress", "password", "password_prompt"])]
+    pub text: bool,
+
+    /// directory where the files embedded with --embed-files are extracted
+    #[arg(long)]
+    pub extract_all: Option<String>,
+
+    /// check the hash chain of the chunks encoded with --chain
+    #[arg(long)]
+    pub verify_chain: bool,
+
+    /// concatenate the data of every chunk of this type, in order, into one message (see encode --chunk-size)
+    #[arg(long, conflicts_with_all = ["extract_all", "verify_chain"])]
+    pub join: bool,
+
+    /// print the messages as hex strings instead of text
+    #[arg(long, conflicts_with_all = ["text", "extract_all", "verify_chain"])]
+    pub hex: bool,
+
+    /// inflate a message encoded with --compress
+    #[arg(long)]
+    pub decompress: bool,
+
+    /// only print the first bytes of the message
+    #[arg(long)]
+    pub max_bytes: Option<usize>,
+
+    #[command(flatten)]
+    pub password: PasswordArgs,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct PrintArgs {
+    /// path to the PNG file 
+    pub filepath: String, 
+
+    /// also print the chunk types grouped by their placement relative to PLTE and IDAT
+    #[arg(long)]
+    pub groups: bool,
+
+    /// also print the file type detected from the magic bytes of each chunk's data
+    #[arg(long)]
+    pub sniff: bool,
+
+    /// also print the number of IDAT chunks and the total size of the image data
+    #[arg(long)]
+    pub stats: bool,
+
+    /// fail if the chunk layout doesn't follow the PNG spec (one IHDR first, one IEND last, no duplicate critical chunk)
+    #[arg(long)]
+    pub strict: bool,
+
+    /// only show the chunks of this 4-letter type (can be repeated)
+    #[arg(long = "type", value_name = "CHUNKTYPE")]
+    pub types: Vec<String>,
+
+    /// only show the critical chunks
+    #[arg(long, conflicts_with = "ancillary_only")]
+    pub critical_only: bool,
+
+    /// only show the ancillary chunks
+    #[arg(long)]
+    pub ancillary_only: bool,
+}
+
+#[derive(clap::Args, Clone, Debug)]
+pub struct PasswordArgs {
+    /// password to encrypt (or decrypt) the message with
+    #[arg(long)]
+    pub password: Option<String>,
+
+    /// read the password from the terminal without echoing it
+    #[arg(long, conflicts_with = "password")]
+    pub password_prompt: bool,
+}
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Encodes a message into a PNG file
-    Encode { 
+    Encode(EncodeArgs),
+    
+    /// Decodes a message from a given chunk in a PNG file
+    Decode(DecodeArgs),
+
+    /// Removes a chunk from a PNG file 
+    Remove { 
+        /// path to the PNG file, or a pattern like "*.png" with --glob
+        filepath: String, 
+        
+        /// 4-letter chunk type
+        chunk_type: String, 
+
+        /// remove the chunk from every PNG file matching the filepath pattern, reporting the failures at the end
+        #[arg(long)]
+        glob: bool,
+
+        /// write the result to a new temporary file and print its path instead of modifying the PNG file
+        #[arg(long)]
+        to_temp: bool,
+
+        /// allow removing the IHDR and IEND chunks, which leaves an invalid PNG file
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Removes the chunk at a given position, as numbered by list
+    RemoveAt { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// index of the chunk among all the chunks of the file
+        index: usize,
+
+        /// allow removing the IHDR and IEND chunks, which leaves an invalid PNG file
+        #[arg(long)]
+        force: bool,
+    },
+
+    /// Changes the type of the first chunk of a given type, keeping its data
+    Rename { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// 4-letter type of the chunk to rename
+        old_type: String, 
+
+        /// new 4-letter chunk type
+        new_type: String, 
+    },
+
+    /// Prints the content of a given png file
+    Print(PrintArgs),
+
+    /// Writes the raw data of a chunk to a file
+    Extract { 
         /// path to the PNG file 
         filepath: String, 
 
         /// 4-letter chunk type
         chunk_type: String, 
 
-        /// message to add to the png file
-        message: String,
+        /// path of the file to write 
+        output: String, 
 
-        // /// output file
-        // out: Option<String>
+        /// index of the chunk among the chunks sharing this type
+        #[arg(long, default_value_t = 0)]
+        index: usize,
     },
-    
-    /// Decodes a message from a given chunk in a PNG file
-    Decode { 
+
+    /// Stores the bytes of a file as they are in a new chunk, to be written back by extract
+    Embed { 
         /// path to the PNG file 
         filepath: String, 
 
         /// 4-letter chunk type
-        chunk_type: String,
+        chunk_type: String, 
+
+        /// path of the file to embed 
+        input: String, 
     },
 
-    /// Removes a chunk from a PNG file 
-    Remove { 
+    /// Converts a custom chunk into a standard tEXt chunk
+    Promote { 
         /// path to the PNG file 
         filepath: String, 
-        
+
         /// 4-letter chunk type
         chunk_type: String, 
+
+        /// keyword of the tEXt chunk
+        keyword: String,
+
+        /// promote every matching chunk instead of only the first one
+        #[arg(long)]
+        all: bool,
     },
 
-    /// Prints the content of a given png file
-    Print { 
+    /// Prints a hexdump of a chunk's data
+    Hexdump { 
         /// path to the PNG file 
         filepath: String, 
+
+        /// 4-letter chunk type
+        chunk_type: String, 
+
+        /// index of the chunk among the chunks sharing this type
+        #[arg(long, default_value_t = 0)]
+        index: usize,
+    },
+
+    /// Searches the data of every chunk for a string
+    Find { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// string to search for
+        query: String,
+
+        /// ignore the case of the query
+        #[arg(long)]
+        ignore_case: bool,
+    },
+
+    /// Prints the keyword and text of every tEXt and zTXt chunk of a PNG file
+    Meta { 
+        /// path to the PNG file 
+        filepath: String, 
+    },
+
+    /// Prints the number of chunks of a PNG file, in total and per type
+    Count { 
+        /// path to the PNG file 
+        filepath: String, 
+    },
+
+    /// Lists the chunks of a PNG file, one per line
+    List { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// only print the chunk types
+        #[arg(long)]
+        type_only: bool,
+
+        /// add the file type detected from the magic bytes of the chunk's data
+        #[arg(long, conflicts_with = "type_only")]
+        sniff: bool,
+    },
+
+    /// Reports the chunks that don't follow the structural conventions of the PNG spec
+    Lint { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// exit with an error if any warning is found
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Removes every ancillary chunk from a PNG file
+    Strip { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// print the removed bytes per chunk type and the file sizes
+        #[arg(long)]
+        report: bool,
+    },
+
+    /// Merges consecutive IDAT chunks to save the overhead of each chunk
+    Optimize { 
+        /// path to the PNG file 
+        filepath: String, 
+    },
+
+    /// Removes the ancillary chunks that repeat the type and data of an earlier chunk
+    Dedup { 
+        /// path to the PNG file 
+        filepath: String, 
+    },
+
+    /// Rewrites the type of standard chunks written with the wrong case (like ihdr) to their canonical case
+    FixCase { 
+        /// path to the PNG file 
+        filepath: String, 
+    },
+
+    /// Writes the EXIF data of the eXIf chunk of a PNG file to a file
+    ExtractExif { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// path of the EXIF file to write 
+        output: String, 
+    },
+
+    /// Lists the chunks added, removed or changed between two PNG files
+    Diff { 
+        /// path to the original PNG file 
+        left: String, 
+
+        /// path to the modified PNG file 
+        right: String, 
+    },
+
+    /// Checks that parsing and serializing a PNG file gives back the exact same bytes
+    RoundtripCheck { 
+        /// path to the PNG file 
+        filepath: String, 
+    },
+
+    /// Checks the CRC and structure of every chunk of a PNG file
+    Verify { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// only print the chunks that failed validation
+        #[arg(long)]
+        only_invalid: bool,
+
+        /// also check the chunk layout required by the PNG spec (one IHDR first, one IEND last, no duplicate critical chunk)
+        #[arg(long)]
+        strict: bool,
+    },
+
+    /// Rewrites a PNG file with the CRC of every chunk recomputed from its type and data
+    Fix { 
+        /// path to the PNG file 
+        filepath: String, 
+    },
+
+    /// Stamps a PNG file with build provenance in a tEXt chunk with the "Build" keyword
+    StampBuild { 
+        /// path to the PNG file 
+        filepath: String, 
+
+        /// commit hash the file was built from
+        #[arg(long)]
+        commit: Option<String>,
+
+        /// build number of the CI pipeline
+        #[arg(long)]
+        build_number: Option<String>,
+
+        /// time of the build
+        #[arg(long)]
+        timestamp: Option<String>,
+    },
+
+    /// Dumps the chunks of a PNG file as JSON
+    Dump { 
+        /// path to the PNG file 
+        filepath: String, 
+    },
+
+    /// Builds a PNG file from a JSON dump
+    Load { 
+        /// path to the JSON dump 
+        json_filepath: String, 
+
+        /// path of the PNG file to write 
+        filepath: String, 
     },
 }
 /// Simple program to encode/decode hidden messages in PNG files
@@ -50,4 +461,79 @@ pub struct Args {
     /// Command to execute 
     #[command(subcommand)]
     pub command: Commands,
+
+    /// number of times to retry writing a file locked by another program
+    #[arg(long, global = true, default_value_t = 0)]
+    pub retry: u32,
+
+    /// write fixed values instead of random data (the encryption salt and nonce)
+    /// so the output is byte-stable across runs. This weakens --password, as every message then shares the same key and nonce
+    #[arg(long, global = true)]
+    pub deterministic: bool,
+
+    /// only print what encode, embed, rename, remove, remove-at, promote, strip, optimize, dedup, fix-case, stamp-build, fix and load would change, without writing any file
+    #[arg(long, global = true)]
+    pub dry_run: bool,
+
+    /// refuse to write files larger than this number of bytes
+    #[arg(long, global = true)]
+    pub max_file_size: Option<u64>,
+
+    /// refuse to parse files with more chunks than this, to bound the memory used by crafted files
+    #[arg(long, global = true, default_value_t = Png::DEFAULT_MAX_CHUNKS)]
+    pub max_chunks: usize,
+
+    /// parse the file even if its PNG signature is invalid (unsafe, for recovering damaged files)
+    #[arg(long, global = true)]
+    pub force_signature: bool,
+
+    /// format of the output of print and decode (rust and c only apply to decode, which prints one JSON object per chunk)
+    #[arg(long, global = true, value_enum, default_value_t = OutputFormat::Text)]
+    pub format: OutputFormat,
+
+    /// when to color the chunks printed by print and list
+    #[arg(long, global = true, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// format of the error printed on failure
+    #[arg(long, global = true, value_enum, default_value_t = ErrorFormat::Text)]
+    pub error_format: ErrorFormat,
+
+    /// only print results and errors, without the status messages and warnings
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// also print debug information such as chunk counts, file sizes and written paths
+    #[arg(short, long, global = true)]
+    pub verbose: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// human readable message
+    Text,
+    /// JSON object with the kind, message and context of the error
+    Json,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// color when printing to a terminal
+    Auto,
+    /// always color, even when piped
+    Always,
+    /// never color
+    Never,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// human readable text
+    Text,
+    /// JSON, with non UTF-8 messages encoded as base64
+    Json,
+    /// Rust byte slice constant
+    Rust,
+    /// C array with its length
+    C,
 }
@@ -0,0 +1,86 @@
+use crate::error::ReasonError;
+use crate::ihdr::ColorType;
+use crate::Result;
+
+use std::fmt::Display;
+
+/// Prefix of the errors of malformed bKGD chunks
+const ERROR_PREFIX: &str = "Bad bKGD";
+
+/// Background color stored in a bKGD chunk, its layout depends on the color type of the image
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum Background {
+    /// Index into the palette (PLTE) of the image
+    PaletteIndex(u8),
+    /// Gray level, with the bit depth of the image
+    Gray(u16),
+    /// Red, green and blue levels, with the bit depth of the image
+    Rgb(u16, u16, u16),
+}
+
+impl Background {
+    /// Parses the data of a bKGD chunk for an image of the given color type
+    pub fn parse(color_type: ColorType, data: &[u8]) -> Result<Background> {
+        let expected = match color_type {
+            ColorType::Palette => 1,
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => 2,
+            ColorType::Rgb | ColorType::Rgba => 6,
+        };
+        if data.len() != expected {
+            return Err(ReasonError::boxed(ERROR_PREFIX, format!(
+                "bKGD data has size {} (expected {} for color type {:?})",
+                data.len(),
+                expected,
+                color_type
+            )));
+        }
+
+        let sample = |index: usize| u16::from_be_bytes([data[index], data[index + 1]]);
+        Ok(match color_type {
+            ColorType::Palette => Background::PaletteIndex(data[0]),
+            ColorType::Grayscale | ColorType::GrayscaleAlpha => Background::Gray(sample(0)),
+            ColorType::Rgb | ColorType::Rgba => Background::Rgb(sample(0), sample(2), sample(4)),
+        })
+    }
+}
+
+impl Display for Background {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Background::PaletteIndex(index) => write!(f, "palette index {}", index),
+            Background::Gray(level) => write!(f, "gray {}", level),
+            Background::Rgb(red, green, blue) => write!(f, "rgb({}, {}, {})", red, green, blue),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_palette_background() {
+        let background = Background::parse(ColorType::Palette, &[3]).unwrap();
+        assert_eq!(background, Background::PaletteIndex(3));
+        assert_eq!(&background.to_string(), "palette index 3");
+    }
+
+    #[test]
+    fn test_gray_background() {
+        let background = Background::parse(ColorType::GrayscaleAlpha, &[1, 0]).unwrap();
+        assert_eq!(background, Background::Gray(256));
+    }
+
+    #[test]
+    fn test_rgb_background() {
+        let background = Background::parse(ColorType::Rgb, &[0, 255, 0, 128, 1, 0]).unwrap();
+        assert_eq!(background, Background::Rgb(255, 128, 256));
+        assert_eq!(&background.to_string(), "rgb(255, 128, 256)");
+    }
+
+    #[test]
+    fn test_invalid_background_size() {
+        assert!(Background::parse(ColorType::Palette, &[3, 4]).is_err());
+        assert!(Background::parse(ColorType::Rgba, &[0, 255]).is_err());
+    }
+}
@@ -0,0 +1,87 @@
+use crate::chunk::Chunk;
+use crate::error::ReasonError;
+use crate::Result;
+
+use sha2::{Digest, Sha256};
+
+/// Prefix of the errors of broken hash chains
+const ERROR_PREFIX: &str = "Bad hash chain";
+
+/// Size of the hash prefixing the data of every chunk of a chain
+pub const HASH_LENGTH: usize = 32;
+
+/// Builds the data of a new chunk of the chain: the SHA-256 of the previous chunk's data
+/// (zeros for the first chunk) followed by the message
+pub fn link(previous: Option<&[u8]>, message: &[u8]) -> Vec<u8> {
+    let hash: [u8; HASH_LENGTH] = match previous {
+        Some(previous) => Sha256::digest(previous).into(),
+        None => [0; HASH_LENGTH],
+    };
+    hash.iter().chain(message).copied().collect()
+}
+
+/// Returns the message stored in the data of a chunk of the chain
+pub fn message(data: &[u8]) -> Result<&[u8]> {
+    data.get(HASH_LENGTH..)
+        .ok_or_else(|| ReasonError::boxed(ERROR_PREFIX, format!("Chunk data is shorter than the {HASH_LENGTH} bytes hash")).into())
+}
+
+/// Checks that every chunk holds the hash of the previous one, in order
+pub fn verify(chunks: &[&Chunk]) -> Result<()> {
+    let mut previous: Option<&[u8]> = None;
+    for (index, chunk) in chunks.iter().enumerate() {
+        message(chunk.data())?;
+        if chunk.data()[..HASH_LENGTH] != link(previous, &[])[..] {
+            return Err(ReasonError::boxed(ERROR_PREFIX, format!(
+                "Chain is broken at chunk {index} (it was reordered or a chunk was removed)"
+            )));
+        }
+        previous = Some(chunk.data());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn chained_chunks(messages: &[&str]) -> Vec<Chunk> {
+        let mut chunks: Vec<Chunk> = vec![];
+        for message in messages {
+            let data = link(chunks.last().map(|chunk| chunk.data()), message.as_bytes());
+            chunks.push(Chunk::new(ChunkType::from_str("chAn").unwrap(), data));
+        }
+        chunks
+    }
+
+    #[test]
+    fn test_intact_chain() {
+        let chunks = chained_chunks(&["first", "second", "third"]);
+        let chunks: Vec<&Chunk> = chunks.iter().collect();
+        assert!(verify(&chunks).is_ok());
+        assert_eq!(message(chunks[1].data()).unwrap(), b"second");
+    }
+
+    #[test]
+    fn test_reordered_chain() {
+        let chunks = chained_chunks(&["first", "second", "third"]);
+        let reordered = vec![&chunks[0], &chunks[2], &chunks[1]];
+        assert!(verify(&reordered).is_err());
+    }
+
+    #[test]
+    fn test_removed_link() {
+        let chunks = chained_chunks(&["first", "second", "third"]);
+        assert!(verify(&[&chunks[0], &chunks[2]]).is_err());
+        assert!(verify(&[&chunks[1], &chunks[2]]).is_err());
+    }
+
+    #[test]
+    fn test_short_data() {
+        let chunk = Chunk::new(ChunkType::from_str("chAn").unwrap(), vec![0; 8]);
+        assert!(message(chunk.data()).is_err());
+        assert!(verify(&[&chunk]).is_err());
+    }
+}
@@ -1,24 +1,78 @@
 use crate::chunk_type::ChunkType;
+use crate::text;
 use crate::Result;
 
 use std::convert::TryFrom;
 use std::fmt::Display;
 use std::error::Error;
 
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
 use crc::{Crc, CRC_32_ISO_HDLC};
 
+const CRC: Crc<u32> = Crc::<u32>::new(&CRC_32_ISO_HDLC);
+
+#[derive(PartialEq, Eq, Hash, Clone)]
 pub struct Chunk {
     chunk_type: ChunkType,
     data: Vec<u8>,
 }
 
 impl Chunk {
+    /// Maximum length of a chunk's data allowed by the PNG spec (2^31 - 1)
+    pub const MAX_LENGTH: usize = 0x7FFF_FFFF;
+
     /// Creates a new chunk based on his type and its data
+    /// The data length isn't checked against `MAX_LENGTH`, see `try_new`. Data longer than `u32::MAX` bytes
+    /// can't be serialized: `length` and `as_bytes` would silently truncate its length
     pub fn new(chunk_type: ChunkType, data: Vec<u8>) -> Chunk {
         Chunk { chunk_type, data }
     }
 
+    /// Creates a new chunk, failing if the data is longer than the PNG spec allows
+    pub fn try_new(chunk_type: ChunkType, data: Vec<u8>) -> Result<Chunk> {
+        Chunk::check_length(data.len())?;
+        Ok(Chunk::new(chunk_type, data))
+    }
+
+    /// Fails if the length exceeds the maximum chunk data length of the PNG spec
+    pub fn check_length(length: usize) -> Result<()> {
+        if length > Chunk::MAX_LENGTH {
+            Err(ChunkEncodingError::boxed(format!("Chunk data length {} exceeds the maximum of {} bytes", length, Chunk::MAX_LENGTH)))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Creates a tEXt chunk from a keyword and its text, both encoded as Latin-1 and separated by a null byte
+    /// The keyword must be 1-79 printable Latin-1 characters without leading or trailing spaces
+    pub fn text(keyword: &str, text: &str) -> Result<Chunk> {
+        Chunk::try_new(ChunkType::from_bytes_unchecked(*b"tEXt"), text::encode_text(keyword, text)?)
+    }
+
+    /// Parses the chunk starting at `offset` in the buffer, which can hold more data after it.
+    /// Returns the chunk and the number of bytes it takes, so that the next chunk starts at `offset` plus this number.
+    /// Failures are reported as a `ChunkDecodingError` holding the offset
+    pub fn from_bytes_at(buffer: &[u8], offset: usize) -> Result<(Chunk, usize)> {
+        let rest = buffer.get(offset..).ok_or_else(|| {
+            ChunkDecodingError::boxed(format!("offset {} is past the end of the {}-byte buffer", offset, buffer.len()))
+        })?;
+        // Let `try_from` report buffers too short for the declared length
+        let size = match rest.get(0..4) {
+            Some(length) => (u32::from_be_bytes(length.try_into()?) as usize).saturating_add(12).min(rest.len()),
+            None => rest.len(),
+        };
+        let chunk = Chunk::try_from(&rest[..size]).map_err(|e| -> crate::Error {
+            match e.downcast::<ChunkDecodingError>() {
+                Ok(e) => e.at_offset(offset),
+                Err(e) => ChunkDecodingError::boxed(e.to_string()).at_offset(offset),
+            }
+        })?;
+        Ok((chunk, size))
+    }
+
     /// Returns the length of the chunk's data
+    /// Truncated for data longer than `u32::MAX` bytes, which only `new` lets through
     pub fn length(&self) -> u32 {
         self.data.len() as u32
     }
@@ -33,14 +87,61 @@ impl Chunk {
         &self.data
     }
 
+    /// Returns the type and the raw data of the chunk, convenient for destructuring
+    ///
+    /// ```
+    /// use pngme::chunk::Chunk;
+    /// use pngme::chunk_type::ChunkType;
+    /// use std::str::FromStr;
+    ///
+    /// let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"hi".to_vec());
+    /// match chunk.parts() {
+    ///     (chunk_type, data) if chunk_type.is_critical() => println!("critical chunk of {} bytes", data.len()),
+    ///     (chunk_type, data) => assert_eq!((chunk_type.to_string().as_str(), data), ("ruSt", &b"hi"[..])),
+    /// }
+    /// ```
+    pub fn parts(&self) -> (&ChunkType, &[u8]) {
+        (&self.chunk_type, &self.data)
+    }
+
+    /// Replaces the raw data of the chunk, the CRC follows the new data
+    pub fn set_data(&mut self, data: Vec<u8>) {
+        self.data = data;
+    }
+
     /// Returns the CRC of the chunk 
-    /// The check includes the type's bytes and the raw data's bytes 
+    /// The check includes the type's bytes and the raw data's bytes, fed to the digest
+    /// without copying them into an intermediate buffer
     pub fn crc(&self) -> u32 {
-        let data: Vec<_> = self.chunk_type.bytes().iter()
-                                        .chain(self.data.iter())
-                                        .copied()
-                                        .collect();
-        Crc::<u32>::new(&CRC_32_ISO_HDLC).checksum(&data)
+        let mut digest = CRC.digest();
+        digest.update(&self.chunk_type.bytes());
+        digest.update(&self.data);
+        digest.finalize()
+    }
+
+    /// Returns the offsets in the data where the needle starts, overlapping matches included.
+    /// With `ignore_case`, UTF-8 data is compared with Unicode case folding and other data with ASCII case folding.
+    /// An empty needle matches nowhere
+    pub fn match_offsets(&self, needle: &[u8], ignore_case: bool) -> Vec<usize> {
+        if needle.is_empty() {
+            return vec![];
+        }
+        if ignore_case {
+            if let (Ok(data), Ok(needle)) = (std::str::from_utf8(&self.data), std::str::from_utf8(needle)) {
+                return data
+                    .char_indices()
+                    .filter(|&(offset, _)| starts_with_ignore_case(&data[offset..], needle))
+                    .map(|(offset, _)| offset)
+                    .collect();
+            }
+        }
+
+        self.data
+            .windows(needle.len())
+            .enumerate()
+            .filter(|(_, window)| if ignore_case { window.eq_ignore_ascii_case(needle) } else { *window == needle })
+            .map(|(offset, _)| offset)
+            .collect()
     }
 
     /// Returns the data as a String
@@ -48,6 +149,19 @@ impl Chunk {
         Ok(String::from_utf8(self.data.clone())?)
     }
 
+    /// Returns the data encoded as standard (padded) base64, which works for data that isn't UTF-8
+    pub fn data_as_base64(&self) -> String {
+        BASE64.encode(&self.data)
+    }
+
+    /// Splits a tEXt chunk's Latin-1 data into its keyword and text on the first null byte
+    pub fn as_text(&self) -> Result<(String, String)> {
+        if &self.chunk_type.bytes() != b"tEXt" {
+            return Err(ChunkDecodingError::boxed(format!("Chunk type {} is not a tEXt chunk", self.chunk_type)));
+        }
+        text::decode_text(&self.data)
+    }
+
     /// Returns the raw bytes of the whole chunk (length + type + data + CRC)
     pub fn as_bytes(&self) -> Vec<u8> {
         self.length()
@@ -61,33 +175,108 @@ impl Chunk {
     }
 }
 
+/// Returns whether the text starts with the prefix, comparing their lowercase forms
+fn starts_with_ignore_case(text: &str, prefix: &str) -> bool {
+    let mut text = text.chars().flat_map(char::to_lowercase);
+    prefix.chars().flat_map(char::to_lowercase).all(|c| text.next() == Some(c))
+}
+
 #[derive(Debug)]
 pub struct ChunkDecodingError {
     reason: String,
+    context: Vec<(&'static str, String)>,
+    offset: Option<usize>,
 }
 impl ChunkDecodingError {
     fn boxed(reason: String) -> Box<Self> {
-        Box::new(Self { reason })
+        Box::new(Self { reason, context: vec![], offset: None })
+    }
+
+    fn with_context(mut self: Box<Self>, key: &'static str, value: String) -> Box<Self> {
+        self.context.push((key, value));
+        self
+    }
+
+    fn at_offset(mut self: Box<Self>, offset: usize) -> Box<Self> {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Returns the position of the chunk that failed to parse in the buffer, when known
+    pub fn offset(&self) -> Option<usize> {
+        self.offset
+    }
+
+    /// Returns the reason of the failure without the "Bad chunk" prefix
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    /// Returns the named values describing the failure (e.g. the expected and actual CRC)
+    pub fn context(&self) -> &[(&'static str, String)] {
+        &self.context
     }
 }
 impl std::fmt::Display for ChunkDecodingError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Bad chunk: {}", self.reason)
+        match self.offset {
+            Some(offset) => write!(f, "Bad chunk at offset {}: {}", offset, self.reason),
+            None => write!(f, "Bad chunk: {}", self.reason),
+        }
     }
 }
 impl Error for ChunkDecodingError {}
 
+#[derive(Debug)]
+pub struct ChunkEncodingError {
+    reason: String,
+}
+impl ChunkEncodingError {
+    fn boxed(reason: String) -> Box<Self> {
+        Box::new(Self { reason })
+    }
+}
+impl std::fmt::Display for ChunkEncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Can't build chunk: {}", self.reason)
+    }
+}
+impl Error for ChunkEncodingError {}
+
 impl TryFrom<&[u8]> for Chunk {
     type Error = crate::Error;
     fn try_from(value: &[u8]) -> std::result::Result<Self, Self::Error> {
+        // Length + type + CRC
+        const OVERHEAD: usize = 12;
+        if value.len() < OVERHEAD {
+            return Err(ChunkDecodingError::boxed(format!("chunk buffer too short: got {} bytes, need at least {}", value.len(), OVERHEAD)));
+        }
+
         let length = u32::from_be_bytes((&value[0..4]).try_into()?);
+        if length as usize > Chunk::MAX_LENGTH {
+            return Err(ChunkDecodingError::boxed(format!("Chunk length {} exceeds the maximum of {} bytes", length, Chunk::MAX_LENGTH)));
+        }
+        // Can't overflow as the length is at most MAX_LENGTH
+        let needed = length as usize + OVERHEAD;
+        if value.len() < needed {
+            return Err(ChunkDecodingError::boxed(format!(
+                "chunk buffer too short: got {} bytes, need {} for a declared length of {}",
+                value.len(),
+                needed,
+                length
+            )));
+        }
         let chunk_type: ChunkType = <[u8; 4]>::try_from(&value[4..8])?.try_into()?;
-        let chunk_data = value[8..8+(length as usize)].iter().copied().collect();
+        let chunk_data = value[8..8+(length as usize)].to_vec();
         let crc = u32::from_be_bytes((&value[8+(length as usize)..]).try_into()?);
 
         let chunk = Chunk::new(chunk_type, chunk_data);
-        if chunk.crc() != crc {
-            Err(ChunkDecodingError::boxed(format!("CRC mismatch (received {}, expected {})", crc, chunk.crc())))
+        let expected_crc = chunk.crc();
+        if expected_crc != crc {
+            Err(ChunkDecodingError::boxed(format!("CRC mismatch (received {}, expected {})", crc, expected_crc))
+                .with_context("chunk_type", chunk.chunk_type().to_string())
+                .with_context("expected_crc", expected_crc.to_string())
+                .with_context("actual_crc", crc.to_string()))
         } else {
             Ok(chunk)
         }
@@ -106,6 +295,15 @@ impl Display for Chunk {
     }
 }
 
+impl std::fmt::Debug for Chunk {
+    /// Prints the data length instead of the data, which can be megabytes long
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Chunk")
+            .field("chunk_type", &format_args!("{}", self.chunk_type))
+            .field("length", &self.data.len())
+            .finish()
+    }
+}
 
 
 #[cfg(test)]
@@ -153,6 +351,14 @@ mod tests {
         assert_eq!(chunk.chunk_type().to_string(), String::from("RuSt"));
     }
 
+    #[test]
+    fn test_chunk_parts() {
+        let chunk = testing_chunk();
+        let (chunk_type, data) = chunk.parts();
+        assert_eq!(chunk_type, chunk.chunk_type());
+        assert_eq!(data, b"This is where your secret message will be!");
+    }
+
     #[test]
     fn test_chunk_string() {
         let chunk = testing_chunk();
@@ -215,6 +421,173 @@ mod tests {
         assert!(chunk.is_err());
     }
 
+    #[test]
+    fn test_crc_large_chunk() {
+        let chunk = Chunk::new(ChunkType::from_str("IDAT").unwrap(), (0..=255).cycle().take(100_000).collect());
+        let buffer: Vec<u8> = chunk.chunk_type().bytes().iter().chain(chunk.data()).copied().collect();
+        assert_eq!(chunk.crc(), CRC.checksum(&buffer));
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture bench_`
+    #[test]
+    #[ignore]
+    fn bench_crc_large_chunk() {
+        let chunk = Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![42; 64 * 1024 * 1024]);
+
+        // The former implementation, which copied the type and data into a new buffer on every call
+        let start = std::time::Instant::now();
+        let buffer: Vec<u8> = chunk.chunk_type().bytes().iter().chain(chunk.data()).copied().collect();
+        let collected = CRC.checksum(&buffer);
+        let collected_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let streamed = chunk.crc();
+        let streamed_time = start.elapsed();
+
+        assert_eq!(collected, streamed);
+        println!("collected: {:?} (allocating {} bytes), crc: {:?} (no allocation)", collected_time, buffer.len(), streamed_time);
+    }
+
+    #[test]
+    fn test_chunk_from_short_buffer() {
+        let chunk = Chunk::try_from(&[0u8, 0, 0][..]);
+        assert!(chunk.is_err());
+        assert!(chunk.err().unwrap().to_string().contains("got 3 bytes, need at least 12"));
+    }
+
+    #[test]
+    fn test_chunk_from_truncated_data() {
+        let length: u32 = 42;
+        let chunk_data: Vec<u8> = length
+            .to_be_bytes()
+            .iter()
+            .chain(b"RuSt".iter())
+            .chain(b"only a part of the data".iter())
+            .chain(0u32.to_be_bytes().iter())
+            .copied()
+            .collect();
+
+        let chunk = Chunk::try_from(chunk_data.as_ref());
+        assert!(chunk.is_err());
+    }
+
+    #[test]
+    fn test_check_length_boundary() {
+        assert!(Chunk::check_length(0).is_ok());
+        assert!(Chunk::check_length(0x7FFF_FFFF).is_ok());
+        assert!(Chunk::check_length(0x8000_0000).is_err());
+    }
+
+    /// Checks the length directly, as allocating more than 4 GiB of data isn't reasonable in a test
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn test_check_length_over_u32_max() {
+        let length = u32::MAX as usize + 1;
+        assert_eq!(length as u32, 0);
+        assert!(Chunk::check_length(length).is_err());
+        assert!(Chunk::check_length(usize::MAX).is_err());
+    }
+
+    #[test]
+    fn test_try_new() {
+        let chunk_type = ChunkType::from_str("RuSt").unwrap();
+        let chunk = Chunk::try_new(chunk_type, b"data".to_vec()).unwrap();
+        assert_eq!(chunk.length(), 4);
+    }
+
+    #[test]
+    fn test_chunk_length_over_spec_max() {
+        let chunk_data: Vec<u8> = 0x8000_0000u32
+            .to_be_bytes()
+            .iter()
+            .chain("RuSt".as_bytes())
+            .chain(&[0, 0, 0, 0])
+            .copied()
+            .collect();
+
+        assert!(Chunk::try_from(chunk_data.as_ref()).is_err());
+    }
+
+    #[test]
+    fn test_text_chunk() {
+        let chunk = Chunk::text("Comment", "Hello").unwrap();
+        assert_eq!(chunk.chunk_type().to_string(), "tEXt");
+        assert_eq!(chunk.data(), b"Comment\0Hello");
+    }
+
+    #[test]
+    fn test_text_chunk_empty_text() {
+        let chunk = Chunk::text("Title", "").unwrap();
+        assert_eq!(chunk.data(), b"Title\0");
+    }
+
+    #[test]
+    fn test_text_chunk_latin1() {
+        let chunk = Chunk::text("Author", "Zoé").unwrap();
+        assert_eq!(chunk.data(), b"Author\0Zo\xe9");
+    }
+
+    #[test]
+    fn test_text_chunk_keyword_length() {
+        assert!(Chunk::text("", "text").is_err());
+        assert!(Chunk::text(&"k".repeat(79), "text").is_ok());
+        assert!(Chunk::text(&"k".repeat(80), "text").is_err());
+    }
+
+    #[test]
+    fn test_text_chunk_invalid_keyword() {
+        assert!(Chunk::text(" Comment", "text").is_err());
+        assert!(Chunk::text("Comment ", "text").is_err());
+        assert!(Chunk::text("Com\nment", "text").is_err());
+        assert!(Chunk::text("Comment", "te\0xt").is_err());
+        assert!(Chunk::text("Comment", "日本").is_err());
+    }
+
+    #[test]
+    fn test_as_text() {
+        let chunk = Chunk::text("Author", "Zoé").unwrap();
+        let (keyword, text) = chunk.as_text().unwrap();
+        assert_eq!(keyword, "Author");
+        assert_eq!(text, "Zoé");
+    }
+
+    #[test]
+    fn test_as_text_splits_on_first_null() {
+        let chunk = Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"Title\0a\0b".to_vec());
+        let (keyword, text) = chunk.as_text().unwrap();
+        assert_eq!(keyword, "Title");
+        assert_eq!(text, "a\0b");
+    }
+
+    #[test]
+    fn test_as_text_missing_separator() {
+        let chunk = Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"Title".to_vec());
+        assert!(chunk.as_text().is_err());
+    }
+
+    #[test]
+    fn test_as_text_wrong_type() {
+        let chunk = testing_chunk();
+        assert!(chunk.as_text().is_err());
+    }
+
+    #[test]
+    fn test_crc_mismatch_context() {
+        let chunk_data: Vec<u8> = 4u32
+            .to_be_bytes()
+            .iter()
+            .chain("RuSt".as_bytes())
+            .chain("data".as_bytes())
+            .chain(&1u32.to_be_bytes())
+            .copied()
+            .collect();
+
+        let error = Chunk::try_from(chunk_data.as_ref()).err().unwrap();
+        let error = error.downcast_ref::<ChunkDecodingError>().unwrap();
+        assert_eq!(error.context()[0], ("chunk_type", "RuSt".to_string()));
+        assert_eq!(error.context()[2], ("actual_crc", "1".to_string()));
+    }
+
     #[test]
     pub fn test_chunk_trait_impls() {
         let data_length: u32 = 42;
@@ -235,4 +608,74 @@ mod tests {
         
         let _chunk_string = format!("{}", chunk);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_chunk_clone() {
+        let chunk = testing_chunk();
+        let clone = chunk.clone();
+        assert_eq!(clone.as_bytes(), chunk.as_bytes());
+    }
+
+    #[test]
+    fn test_chunk_debug() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![0; 1000]);
+        let debug = format!("{:?}", chunk);
+        assert!(debug.contains("RuSt"));
+        assert!(debug.contains("length: 1000"));
+    }
+
+    #[test]
+    fn test_data_as_base64() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![0xff, 0xfe, 0x00, 0x80]);
+        assert!(chunk.data_as_string().is_err());
+        assert_eq!(chunk.data_as_base64(), "//4AgA==");
+    }
+
+    #[test]
+    fn test_crc_follows_set_data() {
+        let mut chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![]);
+        assert_eq!(chunk.crc(), CRC.checksum(b"RuSt"));
+
+        chunk.set_data(b"This is where your secret message will be!".to_vec());
+        assert_eq!(chunk.crc(), 2882656334);
+    }
+
+    #[test]
+    fn test_match_offsets() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"a secret, another Secret".to_vec());
+        assert_eq!(chunk.match_offsets(b"secret", false), [2]);
+        assert_eq!(chunk.match_offsets(b"secret", true), [2, 18]);
+        assert!(chunk.match_offsets(b"public", true).is_empty());
+        assert!(chunk.match_offsets(b"", false).is_empty());
+
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), "Été ÉTÉ".as_bytes().to_vec());
+        assert_eq!(chunk.match_offsets("été".as_bytes(), true), [0, 6]);
+
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), vec![0xff, b'K', b'e', b'Y']);
+        assert_eq!(chunk.match_offsets(b"key", true), [1]);
+    }
+
+    #[test]
+    fn test_from_bytes_at() {
+        let first = testing_chunk();
+        let second = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"second".to_vec());
+        let buffer = [first.as_bytes(), second.as_bytes()].concat();
+
+        let (chunk, consumed) = Chunk::from_bytes_at(&buffer, 0).unwrap();
+        assert_eq!(chunk, first);
+        assert_eq!(consumed, 12 + first.data().len());
+
+        let (chunk, consumed) = Chunk::from_bytes_at(&buffer, consumed).unwrap();
+        assert_eq!(chunk, second);
+        assert_eq!(consumed, 18);
+    }
+
+    #[test]
+    fn test_from_bytes_at_invalid() {
+        let buffer = testing_chunk().as_bytes();
+        assert!(Chunk::from_bytes_at(&buffer, buffer.len()).is_err());
+        assert!(Chunk::from_bytes_at(&buffer, buffer.len() + 1).is_err());
+        assert!(Chunk::from_bytes_at(&buffer[..buffer.len() - 1], 0).is_err());
+        assert!(Chunk::from_bytes_at(&buffer, 1).is_err());
+    }
+}
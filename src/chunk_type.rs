@@ -2,17 +2,74 @@ use std::convert::TryFrom;
 use std::fmt::Display;
 use std::str::FromStr;
 
-#[derive(PartialEq, Eq, Debug)]
+/// Chunk types defined by the PNG spec, with their canonical case
+pub const STANDARD_TYPES: [&[u8; 4]; 19] = [
+    b"IHDR", b"PLTE", b"IDAT", b"IEND", b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB", b"bKGD",
+    b"hIST", b"tRNS", b"pHYs", b"sPLT", b"tIME", b"iTXt", b"tEXt", b"zTXt", b"eXIf",
+];
+
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Clone, Copy)]
 pub struct ChunkType {
     code: [u8; 4],
 }
 
 impl ChunkType {
+    /// Image header, the first chunk of every PNG
+    pub const IHDR: ChunkType = ChunkType::from_bytes_unchecked(*b"IHDR");
+    /// Palette of indexed images
+    pub const PLTE: ChunkType = ChunkType::from_bytes_unchecked(*b"PLTE");
+    /// Compressed image data, possibly split over several consecutive chunks
+    pub const IDAT: ChunkType = ChunkType::from_bytes_unchecked(*b"IDAT");
+    /// Image trailer, the last chunk of every PNG
+    pub const IEND: ChunkType = ChunkType::from_bytes_unchecked(*b"IEND");
+
+    /// Creates a chunk type from its four letters, the same as `ChunkType::try_from`
+    pub fn new(code: [u8; 4]) -> Result<Self, String> {
+        ChunkType::try_from(code)
+    }
+
+    /// Creates a chunk type without checking its bytes, for types known to be valid at compile time (`IHDR`, `IEND`...).
+    /// The caller must pass four ASCII letters: other bytes don't break memory safety,
+    /// but give a chunk type that `is_valid` rejects and that `as_str` prints as an empty string
+    pub const fn from_bytes_unchecked(code: [u8; 4]) -> Self {
+        ChunkType { code }
+    }
+
+    /// Returns a builder applying the property bits to the given four letters
+    pub fn builder(letters: &str) -> ChunkTypeBuilder {
+        ChunkTypeBuilder {
+            letters: letters.to_string(),
+            critical: None,
+            public: None,
+            safe_to_copy: None,
+        }
+    }
+
     /// Returns the raw bytes contained in this chunk
     pub fn bytes(&self) -> [u8; 4] {
         self.code
     }
     
+    /// Returns the four letters of this chunk type, with their exact case
+    pub fn as_str(&self) -> &str {
+        // The code only holds ASCII letters, checked during construction
+        std::str::from_utf8(&self.code).unwrap_or_default()
+    }
+
+    /// Returns true if the PNG spec defines this chunk type, with this exact case
+    pub fn is_standard(&self) -> bool {
+        STANDARD_TYPES.contains(&&self.code)
+    }
+
+    /// Returns the standard chunk type with the same letters in their canonical case, if any
+    /// (`IHDR` for `ihdr`), chunk types that aren't standard have no canonical form
+    pub fn canonical(&self) -> Option<ChunkType> {
+        STANDARD_TYPES
+            .iter()
+            .find(|standard| standard.eq_ignore_ascii_case(&self.code))
+            .map(|standard| ChunkType { code: **standard })
+    }
+
     /// Returns true if the reserved byte is valid and all four bytes are represented by the characters A-Z or a-z.
     /// Note that this chunk type should always be valid as it is validated during construction.
     pub fn is_valid(&self) -> bool {
@@ -40,6 +97,56 @@ impl ChunkType {
     }
 }
 
+/// Builds a chunk type from four letters and its property bits.
+/// Properties that aren't set keep the case of the given letters, the reserved bit is always made valid.
+pub struct ChunkTypeBuilder {
+    letters: String,
+    critical: Option<bool>,
+    public: Option<bool>,
+    safe_to_copy: Option<bool>,
+}
+
+impl ChunkTypeBuilder {
+    /// Sets whether the chunk is critical (uppercase first letter)
+    pub fn critical(mut self, critical: bool) -> Self {
+        self.critical = Some(critical);
+        self
+    }
+
+    /// Sets whether the chunk is public (uppercase second letter)
+    pub fn public(mut self, public: bool) -> Self {
+        self.public = Some(public);
+        self
+    }
+
+    /// Sets whether the chunk is safe to copy (lowercase fourth letter)
+    pub fn safe_to_copy(mut self, safe_to_copy: bool) -> Self {
+        self.safe_to_copy = Some(safe_to_copy);
+        self
+    }
+
+    /// Returns the chunk type, failing if the letters aren't four ASCII letters
+    pub fn build(self) -> Result<ChunkType, String> {
+        let mut code = ChunkType::from_str(&self.letters)?.bytes();
+
+        let set_case = |byte: &mut u8, uppercase: bool| {
+            *byte = if uppercase { byte.to_ascii_uppercase() } else { byte.to_ascii_lowercase() }
+        };
+        if let Some(critical) = self.critical {
+            set_case(&mut code[0], critical);
+        }
+        if let Some(public) = self.public {
+            set_case(&mut code[1], public);
+        }
+        set_case(&mut code[2], true);
+        if let Some(safe_to_copy) = self.safe_to_copy {
+            set_case(&mut code[3], !safe_to_copy);
+        }
+
+        ChunkType::try_from(code)
+    }
+}
+
 impl FromStr for ChunkType {
     type Err = String;
 
@@ -54,7 +161,7 @@ impl FromStr for ChunkType {
         };
 
         let bytes: [u8; 4] = bytes.try_into().unwrap();
-        Ok(ChunkType::try_from(bytes)?)
+        ChunkType::try_from(bytes)
     }
 }
 
@@ -71,7 +178,7 @@ impl TryFrom<[u8; 4]> for ChunkType {
 
 impl Display for ChunkType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", String::from_utf8(self.bytes().to_vec()).expect("Crash in impl Display for ChunkType (how tf do I propagate this error??)"))
+        f.pad(self.as_str())
     }
 }
 
@@ -166,6 +273,76 @@ mod tests {
         assert_eq!(&chunk.to_string(), "RuSt");
     }
 
+    #[test]
+    pub fn test_chunk_type_display_never_panics() {
+        for code in [*b"IHDR", *b"ruSt", *b"zzzz", *b"AAAA"] {
+            let chunk_type = ChunkType::try_from(code).unwrap();
+            assert_eq!(format!("{}", chunk_type).as_bytes(), &code);
+        }
+        let built = ChunkType::builder("rust").critical(true).build().unwrap();
+        assert_eq!(format!("{:>6}", built), "  RuSt");
+    }
+
+    #[test]
+    pub fn test_chunk_type_ordering() {
+        let types: std::collections::BTreeSet<ChunkType> = ["ruSt", "IHDR", "IEND", "bKGD", "IDAT"]
+            .iter()
+            .map(|chunk_type| ChunkType::from_str(chunk_type).unwrap())
+            .collect();
+        let sorted: Vec<String> = types.iter().map(|chunk_type| chunk_type.to_string()).collect();
+        assert_eq!(sorted, ["IDAT", "IEND", "IHDR", "bKGD", "ruSt"]);
+    }
+
+    #[test]
+    pub fn test_chunk_type_copy() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let copy = chunk_type;
+        assert_eq!(copy, chunk_type);
+        assert_eq!(chunk_type.as_str(), "ruSt");
+    }
+
+    #[test]
+    pub fn test_chunk_type_as_str() {
+        let chunk = ChunkType::from_str("ruSt").unwrap();
+        assert_eq!(chunk.as_str(), "ruSt");
+    }
+
+    #[test]
+    pub fn test_chunk_type_canonical() {
+        let canonical = ChunkType::from_str("ihDr").unwrap().canonical().unwrap();
+        assert_eq!(canonical.as_str(), "IHDR");
+        let canonical = ChunkType::from_str("TEXT").unwrap().canonical().unwrap();
+        assert_eq!(canonical.as_str(), "tEXt");
+        assert!(ChunkType::from_str("ruSt").unwrap().canonical().is_none());
+    }
+
+    #[test]
+    pub fn test_chunk_type_builder() {
+        let chunk = ChunkType::builder("RUST")
+            .critical(false)
+            .public(false)
+            .safe_to_copy(true)
+            .build()
+            .unwrap();
+        assert_eq!(&chunk.to_string(), "ruSt");
+        assert!(!chunk.is_critical());
+        assert!(!chunk.is_public());
+        assert!(chunk.is_valid());
+        assert!(chunk.is_safe_to_copy());
+    }
+
+    #[test]
+    pub fn test_chunk_type_builder_keeps_unset_case() {
+        let chunk = ChunkType::builder("Rust").safe_to_copy(false).build().unwrap();
+        assert_eq!(&chunk.to_string(), "RuST");
+    }
+
+    #[test]
+    pub fn test_chunk_type_builder_invalid_letters() {
+        assert!(ChunkType::builder("ru5t").critical(true).build().is_err());
+        assert!(ChunkType::builder("rus").build().is_err());
+    }
+
     #[test]
     pub fn test_chunk_type_trait_impls() {
         let chunk_type_1: ChunkType = TryFrom::try_from([82, 117, 83, 116]).unwrap();
@@ -173,4 +350,41 @@ mod tests {
         let _chunk_string = format!("{}", chunk_type_1);
         let _are_chunks_equal = chunk_type_1 == chunk_type_2;
     }
+
+    #[test]
+    pub fn test_chunk_type_new() {
+        assert_eq!(ChunkType::new(*b"RuSt").unwrap(), ChunkType::from_str("RuSt").unwrap());
+        assert!(ChunkType::new(*b"Ru1t").is_err());
+    }
+
+    #[test]
+    pub fn test_chunk_type_from_bytes_unchecked() {
+        const IHDR: ChunkType = ChunkType::from_bytes_unchecked(*b"IHDR");
+        assert_eq!(IHDR, ChunkType::new(*b"IHDR").unwrap());
+        assert!(IHDR.is_valid());
+
+        // Breaking the contract gives an invalid chunk type instead of an error
+        let invalid = ChunkType::from_bytes_unchecked(*b"Ru1t");
+        assert!(!invalid.is_valid());
+        let invalid = ChunkType::from_bytes_unchecked([0xff, 0, 0, 0]);
+        assert_eq!(invalid.as_str(), "");
+    }
+
+    #[test]
+    pub fn test_critical_constants() {
+        assert_eq!(ChunkType::IEND.to_string(), "IEND");
+        assert!(ChunkType::IEND.is_critical());
+        for chunk_type in [ChunkType::IHDR, ChunkType::PLTE, ChunkType::IDAT, ChunkType::IEND] {
+            assert!(chunk_type.is_valid());
+            assert!(chunk_type.is_critical());
+            assert!(chunk_type.is_standard());
+        }
+    }
+
+    #[test]
+    pub fn test_chunk_type_is_standard() {
+        assert!(ChunkType::from_str("tEXt").unwrap().is_standard());
+        assert!(!ChunkType::from_str("TEXT").unwrap().is_standard());
+        assert!(!ChunkType::from_str("ruSt").unwrap().is_standard());
+    }
 }
@@ -0,0 +1,84 @@
+use crate::error::ReasonError;
+use crate::Result;
+
+use std::io::{self, Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+/// Header byte of a payload stored as is, because compressing it wouldn't make it smaller
+const RAW: u8 = 0;
+/// Header byte of a zlib compressed payload
+const ZLIB: u8 = 1;
+
+/// Prefix of the decompression errors
+const ERROR_PREFIX: &str = "Can't decompress message";
+
+/// Compresses the data into a zlib stream with the best compression
+pub(crate) fn zlib_compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(vec![], Compression::best());
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Inflates a zlib stream, failing with the reason when the stream is invalid
+pub(crate) fn zlib_decompress(data: &[u8]) -> std::result::Result<Vec<u8>, String> {
+    let mut decompressed = vec![];
+    ZlibDecoder::new(data)
+        .read_to_end(&mut decompressed)
+        .map_err(|e| format!("invalid zlib stream ({e})"))?;
+    Ok(decompressed)
+}
+
+/// Compresses the data with zlib, prefixed by a header byte telling `decompress` how it is stored.
+/// Data that doesn't shrink is stored as is behind the header
+pub fn compress(data: &[u8]) -> Result<Vec<u8>> {
+    let compressed = zlib_compress(data)?;
+
+    if compressed.len() < data.len() {
+        Ok([&[ZLIB], compressed.as_slice()].concat())
+    } else {
+        Ok([&[RAW], data].concat())
+    }
+}
+
+/// Returns the original data of a payload built by `compress`
+pub fn decompress(payload: &[u8]) -> Result<Vec<u8>> {
+    match payload.split_first() {
+        Some((&RAW, data)) => Ok(data.to_vec()),
+        Some((&ZLIB, data)) => zlib_decompress(data).map_err(|reason| ReasonError::boxed(ERROR_PREFIX, reason).into()),
+        Some((header, _)) => Err(ReasonError::boxed(ERROR_PREFIX, format!("unknown header byte {header} (was the message compressed?)"))),
+        None => Err(ReasonError::boxed(ERROR_PREFIX, "missing header byte".to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_5kb() {
+        let message = "This is where your secret message will be! ".repeat(120);
+        assert!(message.len() > 5000);
+
+        let payload = compress(message.as_bytes()).unwrap();
+        assert_eq!(payload[0], ZLIB);
+        assert!(payload.len() < message.len() / 10);
+        assert_eq!(decompress(&payload).unwrap(), message.as_bytes());
+    }
+
+    #[test]
+    fn test_incompressible_data_is_stored_raw() {
+        let payload = compress(b"ab").unwrap();
+        assert_eq!(payload, [RAW, b'a', b'b']);
+        assert_eq!(decompress(&payload).unwrap(), b"ab");
+    }
+
+    #[test]
+    fn test_invalid_payload() {
+        assert!(decompress(&[]).is_err());
+        assert!(decompress(&[7, 1, 2]).is_err());
+        assert!(decompress(&[ZLIB, 1, 2, 3]).is_err());
+    }
+}
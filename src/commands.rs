@@ -1 +1,2049 @@
+use crate::args::{Args, ColorChoice, Commands, DecodeArgs, EncodeArgs, OutputFormat, PasswordArgs, PrintArgs};
 
+use pngme::chunk::ChunkDecodingError;
+use pngme::png::{ChunkDiff, ChunkError, PngError};
+use pngme::{archive, chain, codec, crypto, exif, hex, json, lint, sniff, splice, text};
+use pngme::{Chunk, ChunkType, Error, Png, Result};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use indicatif::{ProgressBar, ProgressStyle};
+use log::{debug, error, info, warn};
+use owo_colors::OwoColorize;
+use sha2::{Digest, Sha256};
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IsTerminal, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+
+/// Options shared by every command reading or writing a PNG file
+struct Options {
+    retries: u32,
+    deterministic: bool,
+    force_signature: bool,
+    max_file_size: Option<u64>,
+    dry_run: bool,
+    color: bool,
+    progress: bool,
+    max_chunks: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            retries: 0,
+            deterministic: false,
+            force_signature: false,
+            max_file_size: None,
+            dry_run: false,
+            color: false,
+            progress: false,
+            max_chunks: Png::DEFAULT_MAX_CHUNKS,
+        }
+    }
+}
+
+/// Files at least this large get a progress bar while being parsed
+const PROGRESS_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Returns true if the error is caused by another process holding the file (e.g. an image viewer on Windows).
+/// A denied access to a read-only file won't go away by waiting, so it isn't one
+fn is_lock_error(error: &io::Error, filepath: &str) -> bool {
+    // ERROR_SHARING_VIOLATION and ERROR_LOCK_VIOLATION on Windows
+    (cfg!(windows) && matches!(error.raw_os_error(), Some(32) | Some(33)))
+        || (error.kind() == io::ErrorKind::PermissionDenied
+            && fs::metadata(filepath).is_ok_and(|metadata| !metadata.permissions().readonly()))
+}
+
+/// Reads and parses the PNG file, ignoring its signature when it is forced
+fn read_png(filepath: &str, options: &Options) -> Result<Png> {
+    if options.force_signature {
+        warn!("ignoring the signature of \"{}\", the file may not be a PNG", filepath);
+        Png::try_from_forced_signature_with_limit(&fs::read(filepath)?, options.max_chunks)
+    } else {
+        let size = fs::metadata(filepath)?.len();
+        if options.progress && size >= PROGRESS_THRESHOLD && io::stderr().is_terminal() {
+            read_png_with_progress(filepath, options.max_chunks, ProgressBar::new(size))
+        } else {
+            Png::try_from_path_with_progress(Path::new(filepath), options.max_chunks, |_| {})
+        }
+    }.inspect(|png| debug!("Read {} chunk(s) from \"{}\"", png.len(), filepath))
+}
+
+/// Parses the PNG file while advancing the bar by the bytes of each parsed chunk
+fn read_png_with_progress(filepath: &str, max_chunks: usize, bar: ProgressBar) -> Result<Png> {
+    bar.set_style(ProgressStyle::with_template("Parsing {bar:40} {bytes}/{total_bytes} ({eta})")?);
+    let png = Png::try_from_path_with_progress(Path::new(filepath), max_chunks, |consumed| bar.set_position(consumed as u64));
+    bar.finish_and_clear();
+    png
+}
+
+/// Fails if a file of the given size would exceed the maximum file size
+fn check_file_size(size: u64, options: &Options) -> Result<()> {
+    match options.max_file_size {
+        Some(max_file_size) if size > max_file_size => {
+            Err(format!("Resulting file would be {size} bytes, over the maximum of {max_file_size} bytes").into())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Checks the size of the PNG to write, returning that size
+fn prepare_png(png: &Png, options: &Options) -> Result<u64> {
+    let size = png.total_size() as u64;
+    check_file_size(size, options)?;
+    Ok(size)
+}
+
+/// Writes the PNG to a new uniquely named temporary file that is kept on disk, returning its path
+fn write_temp_png(png: &mut Png, options: &Options) -> Result<PathBuf> {
+    let size = prepare_png(png, options)?;
+    let (file, path) = tempfile::Builder::new()
+        .prefix("pngme-")
+        .suffix(".png")
+        .tempfile()?
+        .keep()?;
+    debug!("Writing {} bytes to \"{}\"", size, path.display());
+    let mut writer = BufWriter::new(file);
+    png.write_to(&mut writer)?;
+    writer.flush()?;
+    Ok(path)
+}
+
+/// Hashes the bytes written to it
+struct HashWriter(Sha256);
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Returns true if the file already holds exactly the bytes of the PNG, comparing their sizes then their hashes.
+/// The file is streamed through the hash rather than parsed
+fn holds_png(filepath: &str, png: &Png, size: u64) -> bool {
+    let file_hash = || -> io::Result<_> {
+        let mut hasher = HashWriter(Sha256::new());
+        io::copy(&mut File::open(filepath)?, &mut hasher)?;
+        Ok(hasher.0.finalize())
+    };
+    let png_hash = || -> io::Result<_> {
+        let mut hasher = HashWriter(Sha256::new());
+        png.write_to(&mut hasher)?;
+        Ok(hasher.0.finalize())
+    };
+    fs::metadata(filepath).is_ok_and(|metadata| metadata.len() == size)
+        && matches!((file_hash(), png_hash()), (Ok(file), Ok(png)) if file == png)
+}
+
+/// Writes the PNG to the given path through a buffer, retrying while the file is locked.
+/// The file isn't touched (keeping its modification time) when it already holds the same bytes
+fn write_png(filepath: &str, png: &mut Png, options: &Options) -> Result<()> {
+    let size = prepare_png(png, options)?;
+    if holds_png(filepath, png, size) {
+        info!("No changes to \"{}\"", filepath);
+        return Ok(());
+    }
+
+    debug!("Writing {} bytes to \"{}\"", size, filepath);
+    with_retries(filepath, options, || {
+        let mut writer = BufWriter::new(File::create(filepath)?);
+        png.write_to(&mut writer)?;
+        writer.flush()
+    })
+}
+
+/// Runs the write to the given path, retrying it with a short backoff while the file is locked
+fn with_retries(filepath: &str, options: &Options, mut write: impl FnMut() -> io::Result<()>) -> Result<()> {
+    let mut attempt = 0;
+    loop {
+        match write() {
+            Ok(()) => {
+                if attempt > 0 {
+                    info!("Wrote \"{}\" after {} retries", filepath, attempt);
+                }
+                return Ok(());
+            },
+            Err(e) if is_lock_error(&e, filepath) && attempt < options.retries => {
+                attempt += 1;
+                thread::sleep(Duration::from_millis(100 * attempt as u64));
+            },
+            Err(e) => {
+                if attempt > 0 {
+                    warn!("Giving up writing \"{}\" after {} retries", filepath, attempt);
+                }
+                return Err(e.into());
+            }
+        }
+    }
+}
+
+/// Rejects chunk types that editors may discard once the image data is modified
+fn check_safe_to_copy(chunk_type: &ChunkType) -> Result<()> {
+    if chunk_type.is_safe_to_copy() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Chunk type \"{chunk_type}\" is not safe to copy: editors may drop it when the image is modified \
+             (use a lowercase fourth letter to mark it safe to copy)"
+        ).into())
+    }
+}
+
+/// Streams the PNG file into a new file with the chunk inserted before IEND, without loading the image.
+/// The new file replaces the PNG file, or is kept as a temporary file whose path is printed.
+fn splice_into_file(filepath: &str, chunk: &Chunk, to_temp: bool, options: &Options) -> Result<()> {
+    let source = File::open(filepath)?;
+    let metadata = source.metadata()?;
+    let size = metadata.len();
+    // Length + type + data + CRC
+    check_file_size(size + 12 + chunk.length() as u64, options)?;
+    if options.dry_run {
+        println!("Would add chunk \"{}\" ({} bytes of data)", chunk.chunk_type(), chunk.length());
+        print_dry_run(filepath, size, size + 12 + chunk.length() as u64);
+        return Ok(());
+    }
+    let source = BufReader::new(source);
+    let builder = tempfile::Builder::new().prefix("pngme-").suffix(".png").clone();
+    let mut output = if to_temp {
+        builder.tempfile()?
+    } else {
+        // Same directory so that the file can be renamed over the PNG file
+        let directory = Path::new(filepath).parent().filter(|parent| !parent.as_os_str().is_empty());
+        builder.tempfile_in(directory.unwrap_or(Path::new(".")))?
+    };
+    splice::splice_chunk(source, BufWriter::new(output.as_file_mut()), chunk)?;
+
+    if to_temp {
+        println!("{}", output.keep()?.1.display());
+        return Ok(());
+    }
+    // Temporary files are only readable by their owner, the PNG file keeps its own permissions
+    fs::set_permissions(output.path(), metadata.permissions())?;
+    let mut output = Some(output);
+    with_retries(filepath, options, || {
+        match output.take().expect("the temporary file is kept until persisted").persist(filepath) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                output = Some(e.file);
+                Err(e.error)
+            }
+        }
+    })
+}
+
+/// Reads `len` bytes of the file starting at `offset` (or up to the end of the file if `len` isn't given)
+fn read_file_range(filepath: &str, offset: u64, len: Option<u64>) -> Result<Vec<u8>> {
+    let mut file = File::open(filepath)?;
+    let size = file.metadata()?.len();
+    let len = len.unwrap_or(size.saturating_sub(offset));
+    if offset.checked_add(len).is_none_or(|end| end > size) {
+        return Err(format!("Range of {len} bytes at offset {offset} is out of \"{filepath}\" ({size} bytes)").into());
+    }
+
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = vec![];
+    file.take(len).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Returns the bytes of the message, read in full from `stdin` if the message is `-`
+fn message_bytes(message: String, mut stdin: impl Read) -> io::Result<Vec<u8>> {
+    if message != "-" {
+        return Ok(message.into_bytes());
+    }
+
+    let mut data = vec![];
+    stdin.read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/// Returns the password given on the command line or typed in the terminal, if any
+fn read_password(args: PasswordArgs) -> Result<Option<String>> {
+    if !args.password_prompt {
+        return Ok(args.password);
+    }
+    if !io::stdin().is_terminal() {
+        return Err("--password-prompt needs an interactive terminal, use --password instead".into());
+    }
+    Ok(Some(rpassword::prompt_password("Password: ")?))
+}
+
+/// Compresses then encrypts the message as requested (encrypted data can't be compressed).
+/// In deterministic mode the encryption uses a fixed salt and nonce
+fn seal(data: Vec<u8>, compress: bool, password: Option<&str>, options: &Options) -> Result<Vec<u8>> {
+    let data = if compress { codec::compress(&data)? } else { data };
+    match password {
+        Some(password) if options.deterministic => crypto::encrypt_deterministic(&data, password),
+        Some(password) => crypto::encrypt(&data, password),
+        None => Ok(data),
+    }
+}
+
+/// Reverts `seal`, decrypting then decompressing the message
+fn unseal(data: &[u8], decompress: bool, password: Option<&str>) -> Result<Vec<u8>> {
+    let data = match password {
+        Some(password) => crypto::decrypt(data, password)?,
+        None => data.to_vec(),
+    };
+    if decompress { codec::decompress(&data) } else { Ok(data) }
+}
+
+fn encode(args: EncodeArgs, options: &Options) -> Result<()> {
+    // With --auto-type, the only positional argument after the path is the message
+    let (chunk_type, message) = match (args.auto_type, args.chunk_type, args.message) {
+        (true, Some(_), Some(_)) => return Err("A chunk type can't be given with --auto-type".into()),
+        (true, message, None) => (None, message),
+        (_, chunk_type, message) => (chunk_type.map(|chunk_type| ChunkType::from_str(&chunk_type)).transpose()?, message),
+    };
+    if args.auto_type && message.is_none() && args.message_file.is_none() && args.embed_files.is_empty() {
+        return Err("No message to encode".into());
+    }
+    // clap can't see the message given in the chunk type slot, so these conflicts are checked here
+    if message.is_some() && (args.message_file.is_some() || !args.embed_files.is_empty()) {
+        return Err("A message can't be given with --message-file or --embed-files".into());
+    }
+    if (args.base64 || args.hex) && message.is_none() {
+        return Err("--base64 and --hex need a message on the command line".into());
+    }
+    let password = read_password(args.password)?;
+
+    if args.stdin {
+        let chunk_type = chunk_type.ok_or("Missing chunk type")?;
+        if args.require_safe_to_copy {
+            check_safe_to_copy(&chunk_type)?;
+        }
+        let mut message = vec![];
+        io::stdin().lock().read_to_end(&mut message)?;
+        let message = seal(message, args.compress, password.as_deref(), options)?;
+        return splice_into_file(&args.filepath, &Chunk::try_new(chunk_type, message)?, args.to_temp, options);
+    }
+
+    let mut png = read_png(&args.filepath, options)?;
+    let output = args.filepath; // For now output is also input
+
+    if let Some(exif_file) = args.exif_file {
+        let data = fs::read(&exif_file)?;
+        if !exif::has_byte_order_mark(&data) {
+            warn!("\"{}\" doesn't start with a TIFF byte-order mark (II or MM)", exif_file);
+        }
+        png.set_exif(data)?;
+        return write_encoded(&output, &mut png, args.to_temp, options);
+    }
+
+    if let Some(text) = args.text {
+        let (keyword, value) = text.split_once('=').ok_or("--text expects keyword=value")?;
+        let chunk = Chunk::text(keyword, value)?;
+        match args.before {
+            Some(index) => png.insert_chunk(index, chunk)?,
+            None => png.append_chunk(chunk),
+        }
+        return write_encoded(&output, &mut png, args.to_temp, options);
+    }
+
+    let chunk_type = match chunk_type {
+        Some(chunk_type) => chunk_type,
+        None => {
+            let chunk_type = png.unused_private_type().ok_or("No unused chunk type left for --auto-type")?;
+            info!("Using chunk type \"{}\"", chunk_type);
+            chunk_type
+        },
+    };
+    if args.require_safe_to_copy {
+        check_safe_to_copy(&chunk_type)?;
+    }
+    if args.if_missing && png.chunk_by_type(chunk_type).is_some() {
+        info!("A chunk with type \"{}\" already exists", chunk_type);
+        return write_encoded(&output, &mut png, args.to_temp, options);
+    }
+
+    let data = match (message, args.message_file) {
+        (Some(message), _) if args.base64 => {
+            let message = message_bytes(message, io::stdin().lock())?;
+            BASE64.decode(message.trim_ascii()).map_err(|e| format!("Invalid base64 message: {e}"))?
+        },
+        (Some(message), _) if args.hex => {
+            let message = String::from_utf8(message_bytes(message, io::stdin().lock())?)?;
+            hex::decode(message.trim()).map_err(|e| format!("Invalid hex message: {e}"))?
+        },
+        (Some(message), _) => message_bytes(message, io::stdin().lock())?,
+        (None, Some(message_file)) => read_file_range(&message_file, args.message_offset, args.message_len)?,
+        (None, None) => archive::pack_files(&args.embed_files)?,
+    };
+    let data = seal(data, args.compress, password.as_deref(), options)?;
+
+    let data = if args.chain {
+        let previous = png.chunks().iter().rfind(|chunk| chunk.chunk_type() == &chunk_type);
+        chain::link(previous.map(|chunk| chunk.data()), &data)
+    } else {
+        data
+    };
+    let pieces = match args.chunk_size {
+        Some(chunk_size) if data.len() > chunk_size.get() => data.chunks(chunk_size.get()).map(<[u8]>::to_vec).collect(),
+        _ => vec![data],
+    };
+    if options.dry_run {
+        let pieces_len: usize = pieces.iter().map(Vec::len).sum();
+        println!("Would add {} chunk(s) \"{}\" ({} bytes of data)", pieces.len(), chunk_type, pieces_len);
+    }
+    for (offset, piece) in pieces.into_iter().enumerate() {
+        let chunk = Chunk::try_new(chunk_type, piece)?;
+        match args.before {
+            Some(index) => png.insert_chunk(index + offset, chunk)?,
+            None => png.append_chunk(chunk),
+        }
+    }
+
+    write_encoded(&output, &mut png, args.to_temp, options)
+} 
+
+/// Prints the size change that writing the file would cause, for --dry-run
+fn print_dry_run(filepath: &str, current_size: u64, new_size: u64) {
+    println!("Dry run: \"{}\" would go from {} to {} bytes, nothing was written", filepath, current_size, new_size);
+}
+
+/// Writes the edited PNG in place, or to a new temporary file whose path is printed.
+/// With --dry-run, only prints the resulting file size
+fn write_encoded(filepath: &str, png: &mut Png, to_temp: bool, options: &Options) -> Result<()> {
+    if options.dry_run {
+        let size = prepare_png(png, options)?;
+        print_dry_run(filepath, fs::metadata(filepath).map_or(0, |metadata| metadata.len()), size);
+        Ok(())
+    } else if to_temp {
+        println!("{}", write_temp_png(png, options)?.display());
+        Ok(())
+    } else {
+        write_png(filepath, png, options)
+    }
+}
+
+/// Quotes the message for printing, or encodes it as base64 when it isn't UTF-8.
+/// The message is cut to `max_bytes` bytes if needed, backing up to a character boundary for text
+fn preview(data: &[u8], max_bytes: Option<usize>) -> String {
+    let max_bytes = max_bytes.unwrap_or(data.len()).min(data.len());
+    let (shown, message) = match std::str::from_utf8(data) {
+        Ok(text) => {
+            let text = &text[..text.floor_char_boundary(max_bytes)];
+            (text.len(), format!("\"{}\"", text))
+        },
+        Err(_) => (max_bytes, format!("(base64) {}", BASE64.encode(&data[..max_bytes]))),
+    };
+    message + &truncation_note(shown, data.len())
+}
+
+/// Encodes the message as hex, cut to `max_bytes` bytes if needed like `preview`
+fn hex_preview(data: &[u8], max_bytes: Option<usize>) -> String {
+    let max_bytes = max_bytes.unwrap_or(data.len()).min(data.len());
+    hex::encode(&data[..max_bytes]) + &truncation_note(max_bytes, data.len())
+}
+
+/// Tells how many bytes of the message are shown, when it was cut
+fn truncation_note(shown: usize, total: usize) -> String {
+    if shown < total {
+        format!(" (truncated to {} of {} bytes)", shown, total)
+    } else {
+        String::new()
+    }
+}
+
+/// Cuts the message to `max_bytes` bytes for the json, rust and c formats, which can't hold a note,
+/// so the truncation is reported on stderr instead
+fn truncate(data: &[u8], max_bytes: Option<usize>) -> &[u8] {
+    let max_bytes = max_bytes.unwrap_or(data.len()).min(data.len());
+    if max_bytes < data.len() {
+        warn!("Message truncated to {} of {} bytes", max_bytes, data.len());
+    }
+    &data[..max_bytes]
+}
+
+/// Formats the bytes as comma separated hex literals, 12 per indented line
+fn byte_rows(data: &[u8]) -> String {
+    data.chunks(12)
+        .map(|row| {
+            let bytes: Vec<String> = row.iter().map(|byte| format!("0x{:02x},", byte)).collect();
+            format!("    {}\n", bytes.join(" "))
+        })
+        .collect()
+}
+
+/// Formats the data as a Rust byte slice constant
+fn rust_literal(name: &str, data: &[u8]) -> String {
+    format!("const {}: &[u8] = &[\n{}];\n", name.to_uppercase(), byte_rows(data))
+}
+
+/// Formats the data as a C array followed by its length, like `xxd -i`
+fn c_array(name: &str, data: &[u8]) -> String {
+    format!(
+        "unsigned char {0}[] = {{\n{1}}};\nunsigned int {0}_len = {2};\n",
+        name.to_lowercase(),
+        byte_rows(data),
+        data.len()
+    )
+}
+
+fn decode(args: DecodeArgs, format: OutputFormat, options: &Options) -> Result<()> {
+    let png = read_png(&args.filepath, options)?;
+    if args.text {
+        for chunk in png.chunks_by_type(ChunkType::from_str("tEXt")?) {
+            let (keyword, text) = chunk.as_text()?;
+            println!("{}={}", keyword, text);
+        }
+        return Ok(());
+    }
+
+    let chunk_type = args.chunk_type.ok_or("Missing chunk type")?;
+    let password = read_password(args.password)?;
+    let reveal = |data: &[u8]| unseal(data, args.decompress, password.as_deref());
+
+    if args.verify_chain {
+        let chunk_type = ChunkType::from_str(&chunk_type[..])?;
+        let chunks: Vec<&Chunk> = png.chunks().iter().filter(|chunk| chunk.chunk_type() == &chunk_type).collect();
+        chain::verify(&chunks)?;
+        println!("Hash chain of {} chunk(s) \"{}\" is intact", chunks.len(), chunk_type);
+        for chunk in chunks {
+            println!("Found hidden message: {} in chunk \"{}\"", preview(&reveal(chain::message(chunk.data())?)?, args.max_bytes), chunk_type);
+        }
+        return Ok(());
+    }
+
+    let chunks = png.chunks_by_type(ChunkType::from_str(&chunk_type[..])?);
+    if chunks.is_empty() {
+        return Err(format!("No chunk found with type \"{}\"", chunk_type).into());
+    }
+
+    if args.join {
+        let data: Vec<u8> = chunks.iter().flat_map(|chunk| chunk.data()).copied().collect();
+        let data = reveal(&data)?;
+        let name = format!("{}_data", chunk_type);
+        match format {
+            OutputFormat::Text if args.hex => println!("Found hidden message (hex): {} in {} chunk(s) \"{}\"", hex_preview(&data, args.max_bytes), chunks.len(), chunk_type),
+            OutputFormat::Text => println!("Found hidden message: {} in {} chunk(s) \"{}\"", preview(&data, args.max_bytes), chunks.len(), chunk_type),
+            OutputFormat::Json => println!("{}", json::message(chunks[0].chunk_type(), truncate(&data, args.max_bytes))?),
+            OutputFormat::Rust => print!("{}", rust_literal(&name, truncate(&data, args.max_bytes))),
+            OutputFormat::C => print!("{}", c_array(&name, truncate(&data, args.max_bytes))),
+        }
+        return Ok(());
+    }
+
+    for (index, chunk) in chunks.iter().enumerate() {
+        if format != OutputFormat::Text {
+            let data = reveal(chunk.data())?;
+            let data = truncate(&data, args.max_bytes);
+            let name = if chunks.len() > 1 { format!("{}_data_{}", chunk_type, index) } else { format!("{}_data", chunk_type) };
+            match format {
+                OutputFormat::Json => println!("{}", json::message(chunk.chunk_type(), data)?),
+                OutputFormat::Rust => print!("{}", rust_literal(&name, data)),
+                _ => print!("{}", c_array(&name, data)),
+            }
+            continue;
+        }
+
+        match &args.extract_all {
+            Some(directory) => {
+                for name in archive::extract_all(&reveal(chunk.data())?, Path::new(directory))? {
+                    println!("Extracted \"{}\" from chunk {} \"{}\" into \"{}\"", name, index, chunk_type, directory);
+                }
+            },
+            None if chunk_type == "eXIf" => {
+                println!("Found EXIF data {} ({} bytes) in chunk \"{}\"", index, chunk.data().len(), chunk_type);
+            },
+            None if chunk_type == "tEXt" => {
+                match chunk.as_text()? {
+                    (keyword, text) if keyword == BUILD_KEYWORD => println!("Found build stamp {}:\n{}", index, text),
+                    (keyword, text) => println!("Found text {}: \"{}\" with keyword \"{}\" in chunk \"{}\"", index, text, keyword, chunk_type),
+                }
+            },
+            None if args.hex => {
+                let message = hex_preview(&reveal(chunk.data())?, args.max_bytes);
+                println!("Found hidden message {} (hex): {} in chunk \"{}\"", index, message, chunk_type);
+            },
+            None => {
+                let message = preview(&reveal(chunk.data())?, args.max_bytes);
+                println!("Found hidden message {}: {} in chunk \"{}\"", index, message, chunk_type);
+            },
+        }
+    }
+
+    Ok(())
+}
+
+fn remove(filepath: String, chunk_type: String, to_temp: bool, force: bool, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    let parsed_type = ChunkType::from_str(&chunk_type[..])?;
+    let chunk = if force { png.force_remove_chunk(parsed_type) } else { png.remove_chunk(parsed_type) };
+
+    match chunk {
+        Ok(chunk) => {
+            let verb = if options.dry_run { "Would remove" } else { "Removed" };
+            let message = format!("{} hidden message: \"{}\" in chunk \"{}\"", verb, String::from_utf8_lossy(chunk.data()), chunk_type);
+            info!("{}", message);
+            write_encoded(&filepath, &mut png, to_temp, options)?
+        },
+        Err(e) if !force && Png::is_protected(&parsed_type) => return Err(format!("{e} (use --force to remove it anyway)").into()),
+        Err(e) => info!("No chunk found with type \"{}\" (got error {})", chunk_type, e)
+    }
+
+    Ok(())
+}
+
+fn remove_at(filepath: String, index: usize, force: bool, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    match png.chunk_by_index(index) {
+        Some(chunk) if !force && Png::is_protected(chunk.chunk_type()) => {
+            return Err(format!("Refusing to remove critical chunk {} (use --force to remove it anyway)", chunk.chunk_type()).into());
+        },
+        _ => {},
+    }
+
+    let chunk = png.remove_chunk_by_index(index)?;
+    let verb = if options.dry_run { "Would remove" } else { "Removed" };
+    info!("{} chunk {} \"{}\" ({} bytes)", verb, index, chunk.chunk_type(), chunk.length());
+    write_encoded(&filepath, &mut png, false, options)
+}
+
+fn rename(filepath: String, old_type: String, new_type: String, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    let old_type = ChunkType::from_str(&old_type[..])?;
+    let new_type = ChunkType::from_str(&new_type[..])?;
+    png.rename_chunk(old_type, new_type)?;
+
+    info!("Renamed chunk \"{}\" to \"{}\"", old_type, new_type);
+    write_encoded(&filepath, &mut png, false, options)
+}
+
+/// Replaces chunks of the given type by tEXt chunks holding the same data under the given keyword.
+/// Returns the number of promoted chunks.
+fn promote_chunks(png: &mut Png, chunk_type: &str, keyword: &str, all: bool) -> Result<usize> {
+    if chunk_type == "tEXt" {
+        return Err(format!("Chunk \"{chunk_type}\" is already a standard text chunk").into());
+    }
+
+    let chunk_type = ChunkType::from_str(chunk_type)?;
+    text::check_keyword(keyword)?;
+
+    let found = png.chunks_by_type(chunk_type).len();
+    let count = if all { found } else { found.min(1) };
+    for _ in 0..count {
+        let chunk = png.remove_chunk(chunk_type)?;
+        png.append_chunk(Chunk::text(keyword, &chunk.data_as_string()?)?);
+    }
+    Ok(count)
+}
+
+fn promote(filepath: String, chunk_type: String, keyword: String, all: bool, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    match promote_chunks(&mut png, &chunk_type, &keyword, all)? {
+        0 => info!("No chunk found with type \"{}\"", chunk_type),
+        count => {
+            info!("Promoted {} chunk(s) \"{}\" to tEXt with keyword \"{}\"", count, chunk_type, keyword);
+            write_encoded(&filepath, &mut png, false, options)?
+        }
+    }
+
+    Ok(())
+}
+
+/// Formats bytes as an xxd-style dump: offset, 16 bytes in hex and their ASCII representation per line
+fn hexdump(data: &[u8]) -> String {
+    let mut dump = String::new();
+    for (line, bytes) in data.chunks(16).enumerate() {
+        let hex: Vec<String> = bytes
+            .chunks(2)
+            .map(|pair| pair.iter().map(|byte| format!("{:02x}", byte)).collect())
+            .collect();
+        let ascii: String = bytes
+            .iter()
+            .map(|&byte| if byte.is_ascii_graphic() || byte == b' ' { byte as char } else { '.' })
+            .collect();
+        dump.push_str(&format!("{:08x}: {:<39}  {}\n", line * 16, hex.join(" "), ascii));
+    }
+    dump
+}
+
+fn hexdump_chunk(filepath: String, chunk_type: String, index: usize, options: &Options) -> Result<()> {
+    let png = read_png(&filepath, options)?;
+    let chunk_type = ChunkType::from_str(&chunk_type[..])?;
+    let chunk = png.chunks()
+                    .iter()
+                    .filter(|chunk| chunk.chunk_type() == &chunk_type)
+                    .nth(index)
+                    .ok_or_else(|| format!("No chunk found with type \"{}\" at index {}", chunk_type, index))?;
+
+    print!("{}", hexdump(chunk.data()));
+    Ok(())
+}
+
+fn extract(filepath: String, chunk_type: String, output: String, index: usize, options: &Options) -> Result<()> {
+    let png = read_png(&filepath, options)?;
+    let chunk_type = ChunkType::from_str(&chunk_type[..])?;
+    let chunk = png.chunks_by_type(chunk_type)
+        .into_iter()
+        .nth(index)
+        .ok_or_else(|| format!("No chunk found with type \"{}\" at index {}", chunk_type, index))?;
+
+    fs::write(&output, chunk.data())?;
+    info!("Extracted {} bytes of chunk \"{}\" into \"{}\"", chunk.length(), chunk_type, output);
+    Ok(())
+}
+
+/// Appends a chunk holding the raw bytes of the input file, without any text encoding
+fn embed(filepath: String, chunk_type: String, input: String, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    let chunk_type = ChunkType::from_str(&chunk_type[..])?;
+    let data = fs::read(&input)?;
+    Chunk::check_length(data.len())?;
+
+    info!("Embedding {} bytes of \"{}\" into chunk \"{}\"", data.len(), input, chunk_type);
+    png.append_chunk(Chunk::new(chunk_type, data));
+    write_encoded(&filepath, &mut png, false, options)
+}
+
+/// Decides whether to color the output, only coloring terminals in auto mode so that pipes stay clean
+fn use_color(choice: ColorChoice) -> bool {
+    match choice {
+        ColorChoice::Auto => io::stdout().is_terminal(),
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+    }
+}
+
+/// Colors the text describing a chunk: yellow for critical chunks, cyan for ancillary ones
+fn paint(chunk_type: &ChunkType, text: &str, color: bool) -> String {
+    match (color, chunk_type.is_critical()) {
+        (false, _) => text.to_string(),
+        (true, true) => text.yellow().to_string(),
+        (true, false) => text.cyan().to_string(),
+    }
+}
+
+/// Formats the PNG like its `Display` implementation, with each chunk colored by `paint`
+fn render_png(png: &Png, color: bool) -> String {
+    let chunks: String = png.chunks().iter().map(|chunk| paint(chunk.chunk_type(), &chunk.to_string(), color)).collect();
+    format!("PNG File {{{}}}", chunks)
+}
+
+/// Describes a chunk on one line: its type, length, CRC and whether it's critical or ancillary
+fn list_line(chunk: &Chunk, sniff: bool) -> String {
+    let kind = if chunk.chunk_type().is_critical() { "critical" } else { "ancillary" };
+    let line = format!("{}  {}  {}  {}", chunk.chunk_type(), chunk.length(), chunk.crc(), kind);
+    if sniff {
+        format!("{}  {}", line, sniff::sniff(chunk.data()))
+    } else {
+        line
+    }
+}
+
+fn find(filepath: String, query: String, ignore_case: bool, options: &Options) -> Result<()> {
+    if query.is_empty() {
+        return Err("The query can't be empty".into());
+    }
+
+    let png = read_png(&filepath, options)?;
+    let found = if ignore_case { png.find_ignore_case(query.as_bytes()) } else { png.find(query.as_bytes()) };
+    if found.is_empty() {
+        println!("No chunk contains \"{}\"", query);
+    }
+    for (index, chunk) in found {
+        let offsets: Vec<String> = chunk.match_offsets(query.as_bytes(), ignore_case).iter().map(usize::to_string).collect();
+        println!("Chunk {} \"{}\" at offset(s) {}", index, chunk.chunk_type(), offsets.join(", "));
+    }
+
+    Ok(())
+}
+
+fn meta(filepath: String, options: &Options) -> Result<()> {
+    let png = read_png(&filepath, options)?;
+    let texts = png.text_chunks();
+    if texts.is_empty() {
+        info!("No tEXt chunk in \"{}\"", filepath);
+    }
+    for (keyword, text) in texts {
+        println!("{}: {}", keyword, text);
+    }
+    Ok(())
+}
+
+fn count(filepath: String, options: &Options) -> Result<()> {
+    let png = read_png(&filepath, options)?;
+    println!("{} chunk(s)", png.len());
+    for (chunk_type, count) in png.count_by_type() {
+        println!("{}  {}", chunk_type, count);
+    }
+
+    Ok(())
+}
+
+fn list(filepath: String, type_only: bool, sniff: bool, options: &Options) -> Result<()> {
+    let png = read_png(&filepath, options)?;
+    for chunk in png.chunks() {
+        let line = if type_only { chunk.chunk_type().to_string() } else { list_line(chunk, sniff) };
+        println!("{}", paint(chunk.chunk_type(), &line, options.color));
+    }
+
+    Ok(())
+}
+
+fn lint(filepath: String, strict: bool, options: &Options) -> Result<()> {
+    let png = read_png(&filepath, options)?;
+    let warnings = lint::lint(&png);
+    for warning in &warnings {
+        println!("Warning: {}", warning);
+    }
+
+    if strict && !warnings.is_empty() {
+        return Err(format!("Found {} warning(s) in \"{}\"", warnings.len(), filepath).into());
+    }
+
+    Ok(())
+}
+
+/// Formats a table of the removed chunks per type with the bytes they took, followed by the file sizes
+fn strip_report(removed: &[Chunk], size_before: usize, size_after: usize) -> String {
+    let mut per_type: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+    for chunk in removed {
+        let entry = per_type.entry(chunk.chunk_type().to_string()).or_default();
+        entry.0 += 1;
+        // Length + type + data + CRC
+        entry.1 += 12 + chunk.data().len();
+    }
+
+    let mut report = format!("{:<6}{:>8}{:>12}\n", "Type", "Count", "Bytes");
+    for (chunk_type, (count, bytes)) in &per_type {
+        report.push_str(&format!("{:<6}{:>8}{:>12}\n", chunk_type, count, bytes));
+    }
+    report.push_str(&format!("{:<6}{:>8}{:>12}\n", "Total", removed.len(), size_before - size_after));
+    report.push_str(&format!("Size: {} -> {} bytes\n", size_before, size_after));
+    report
+}
+
+fn strip(filepath: String, report: bool, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    let size_before = png.total_size();
+    let removed = png.retain_critical();
+    write_encoded(&filepath, &mut png, false, options)?;
+
+    if report {
+        print!("{}", strip_report(&removed, size_before, png.total_size()));
+    } else {
+        info!("Removed {} ancillary chunk(s)", removed.len());
+    }
+
+    Ok(())
+}
+
+fn optimize(filepath: String, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    let size_before = png.total_size();
+    let merged = png.merge_idat();
+    if merged > 0 {
+        write_encoded(&filepath, &mut png, false, options)?;
+    }
+
+    let size_after = png.total_size();
+    let (verb, saved) = if options.dry_run { ("Would remove", "would save") } else { ("Removed", "saved") };
+    println!("{} {} IDAT chunk(s) by merging, {} {} bytes ({} -> {} bytes)", verb, merged, saved, size_before - size_after, size_before, size_after);
+    Ok(())
+}
+
+fn dedup(filepath: String, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    let removed = png.dedup_chunks();
+    if removed > 0 {
+        write_encoded(&filepath, &mut png, false, options)?;
+    }
+    let verb = if options.dry_run { "Would remove" } else { "Removed" };
+    println!("{} {} duplicate chunk(s)", verb, removed);
+    Ok(())
+}
+
+fn fix_case(filepath: String, options: &Options) -> Result<()> {
+    let mut png = read_png(&filepath, options)?;
+    let corrected = png.fix_case();
+    for (index, previous) in &corrected {
+        info!("Corrected chunk {} ({} -> {})", index, previous, png.chunks()[*index].chunk_type());
+    }
+
+    if corrected.is_empty() {
+        info!("Every chunk type already has its canonical case");
+        Ok(())
+    } else {
+        write_encoded(&filepath, &mut png, false, options)
+    }
+}
+
+fn extract_exif(filepath: String, output: String, options: &Options) -> Result<()> {
+    let png = read_png(&filepath, options)?;
+    let data = png.exif().ok_or_else(|| format!("No eXIf chunk found in \"{}\"", filepath))?;
+    if !exif::has_byte_order_mark(data) {
+        warn!("the EXIF data doesn't start with a TIFF byte-order mark (II or MM)");
+    }
+    fs::write(&output, data)?;
+    info!("Extracted {} bytes of EXIF data into \"{}\"", data.len(), output);
+    Ok(())
+}
+
+/// Describes a difference between two PNGs on one line, with the sizes and CRCs of changed chunks
+fn diff_line(left: &Png, right: &Png, diff: &ChunkDiff) -> String {
+    match *diff {
+        ChunkDiff::Added { index, chunk_type } => {
+            format!("+ chunk {} \"{}\" ({} bytes)", index, chunk_type, right.chunks()[index].length())
+        },
+        ChunkDiff::Removed { index, chunk_type } => {
+            format!("- chunk {} \"{}\" ({} bytes)", index, chunk_type, left.chunks()[index].length())
+        },
+        ChunkDiff::Changed { index, other_index, chunk_type } => {
+            let (before, after) = (&left.chunks()[index], &right.chunks()[other_index]);
+            format!(
+                "~ chunk {} -> {} \"{}\" ({} -> {} bytes, CRC {} -> {})",
+                index, other_index, chunk_type, before.length(), after.length(), before.crc(), after.crc()
+            )
+        },
+    }
+}
+
+fn diff(left: String, right: String, options: &Options) -> Result<()> {
+    let (left_png, right_png) = (read_png(&left, options)?, read_png(&right, options)?);
+    let diffs = left_png.diff(&right_png);
+    for diff in &diffs {
+        println!("{}", diff_line(&left_png, &right_png, diff));
+    }
+    if diffs.is_empty() {
+        println!("No differences between \"{}\" and \"{}\"", left, right);
+    }
+
+    Ok(())
+}
+
+/// Returns the offset of the first byte that differs between the two buffers, if any
+fn first_difference(left: &[u8], right: &[u8]) -> Option<usize> {
+    match left.iter().zip(right).position(|(left, right)| left != right) {
+        Some(offset) => Some(offset),
+        None if left.len() != right.len() => Some(left.len().min(right.len())),
+        None => None,
+    }
+}
+
+/// Parses the file leniently then serializes it again, so that the bytes a strict parse would reject
+/// (bad signature, wrong CRCs, trailing data) show up as differences
+fn roundtrip_check(filepath: String, options: &Options) -> Result<()> {
+    let input_bytes = fs::read(&filepath)?;
+    let (png, errors) = Png::try_from_lenient_with_limit(&input_bytes, options.max_chunks);
+    for error in &errors {
+        debug!("{}", error);
+    }
+    let output_bytes = png.as_bytes();
+
+    match first_difference(&input_bytes, &output_bytes) {
+        None => {
+            println!("Round trip of \"{}\" is byte-identical ({} bytes)", filepath, input_bytes.len());
+            Ok(())
+        },
+        Some(offset) => Err(format!(
+            "Round trip of \"{}\" differs at offset {} (read {} bytes, wrote {} bytes)",
+            filepath, offset, input_bytes.len(), output_bytes.len()
+        ).into()),
+    }
+}
+
+/// Describes the result of the lenient parse, one line per chunk or per problem
+fn verify_report(png: &Png, errors: &[ChunkError], only_invalid: bool) -> Vec<String> {
+    let mut lines: Vec<String> = errors
+        .iter()
+        .filter(|error| error.index.is_none())
+        .map(|error| format!("INVALID {}", error))
+        .collect();
+
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        match errors.iter().find(|error| error.index == Some(index)) {
+            Some(error) => lines.push(format!("INVALID chunk {} ({}) {}", index, chunk.chunk_type(), error)),
+            None if !only_invalid => lines.push(format!("OK      chunk {} ({})", index, chunk.chunk_type())),
+            None => {},
+        }
+    }
+    lines
+}
+
+fn verify(filepath: String, only_invalid: bool, strict: bool, options: &Options) -> Result<()> {
+    let input_bytes = fs::read(&filepath)?;
+
+    let (png, errors) = Png::try_from_lenient_with_limit(&input_bytes, options.max_chunks);
+    for line in verify_report(&png, &errors, only_invalid) {
+        println!("{}", line);
+    }
+    let layout_problems = if strict { png.validate().err().unwrap_or_default() } else { vec![] };
+    for problem in &layout_problems {
+        println!("INVALID {}", problem);
+    }
+    let problems = errors.len() + layout_problems.len();
+    println!("Checked {} chunk(s): {} problem(s)", png.len(), problems);
+
+    if problems == 0 {
+        Ok(())
+    } else {
+        Err(format!("Found {} problem(s) in \"{}\"", problems, filepath).into())
+    }
+}
+
+/// Rewrites the PNG file with recomputed CRCs. Other problems are refused, since their chunks would be lost
+fn fix(filepath: String, options: &Options) -> Result<()> {
+    let (mut png, errors) = Png::try_from_lenient_with_limit(&fs::read(&filepath)?, options.max_chunks);
+    let unfixable: Vec<&ChunkError> = errors.iter().filter(|error| error.index.is_none()).collect();
+    if !unfixable.is_empty() {
+        for error in &unfixable {
+            error!("Can't fix {}", error);
+        }
+        return Err(format!("Found {} problem(s) other than CRC mismatches in \"{}\"", unfixable.len(), filepath).into());
+    }
+
+    write_encoded(&filepath, &mut png, false, options)?;
+    let verb = if options.dry_run { "Would correct" } else { "Corrected" };
+    println!("{} {} CRC(s) in \"{}\"", verb, errors.len(), filepath);
+    Ok(())
+}
+
+fn dump(filepath: String, options: &Options) -> Result<()> {
+    let png = read_png(&filepath, options)?;
+    println!("{}", json::dump(&png)?);
+    Ok(())
+}
+
+fn load(json_filepath: String, filepath: String, options: &Options) -> Result<()> {
+    let mut png = json::load(&fs::read_to_string(json_filepath)?)?;
+    write_encoded(&filepath, &mut png, false, options)
+}
+
+/// Keyword of the tEXt chunk holding the build provenance
+const BUILD_KEYWORD: &str = "Build";
+
+/// Formats the provenance values as one "name: value" line each, skipping the missing ones
+fn build_stamp(commit: Option<String>, build_number: Option<String>, timestamp: Option<String>) -> Result<String> {
+    let lines: Vec<String> = [("commit", commit), ("build", build_number), ("timestamp", timestamp)]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| format!("{}: {}", name, value)))
+        .collect();
+
+    if lines.is_empty() {
+        return Err("At least one of --commit, --build-number or --timestamp is required".into());
+    }
+    Ok(lines.join("\n"))
+}
+
+fn stamp_build(filepath: String, commit: Option<String>, build_number: Option<String>, timestamp: Option<String>, options: &Options) -> Result<()> {
+    let stamp = build_stamp(commit, build_number, timestamp)?;
+
+    let mut png = read_png(&filepath, options)?;
+    png.append_chunk(Chunk::text(BUILD_KEYWORD, &stamp)?);
+    write_encoded(&filepath, &mut png, false, options)?;
+
+    info!("Stamped \"{}\" with:\n{}", filepath, stamp);
+    Ok(())
+}
+
+/// Builds the filter of the chunks selected by the --type, --critical-only and --ancillary-only options of print
+fn chunk_filter(args: &PrintArgs) -> Result<impl Fn(&Chunk) -> bool + '_> {
+    let types = args.types
+        .iter()
+        .map(|chunk_type| ChunkType::from_str(chunk_type))
+        .collect::<std::result::Result<Vec<ChunkType>, String>>()?;
+
+    Ok(move |chunk: &Chunk| {
+        (types.is_empty() || types.contains(chunk.chunk_type()))
+            && (!args.critical_only || chunk.chunk_type().is_critical())
+            && (!args.ancillary_only || !chunk.chunk_type().is_critical())
+    })
+}
+
+/// Returns the chunks selected by the --type, --critical-only and --ancillary-only filters of print,
+/// or `None` when no filter is given
+fn select_chunks(png: &Png, args: &PrintArgs) -> Result<Option<Png>> {
+    if args.types.is_empty() && !args.critical_only && !args.ancillary_only {
+        return Ok(None);
+    }
+    let selected = chunk_filter(args)?;
+    let chunks = png.chunks().iter().filter(|chunk| selected(chunk)).cloned().collect();
+    Ok(Some(Png::from_chunks(chunks)))
+}
+
+fn print(args: PrintArgs, format: OutputFormat, options: &Options) -> Result<()> {
+    let png = read_png(&args.filepath, options)?;
+    if args.strict {
+        if let Err(problems) = png.validate() {
+            return Err(format!("\"{}\" doesn't follow the PNG spec: {}", args.filepath, problems.join(", ")).into());
+        }
+    }
+    let selected = select_chunks(&png, &args)?;
+    let listed = selected.as_ref().unwrap_or(&png);
+    match format {
+        OutputFormat::Text => {},
+        OutputFormat::Json => {
+            println!("{}", json::summary(listed)?);
+            return Ok(());
+        },
+        OutputFormat::Rust | OutputFormat::C => return Err("print only supports --format text or json".into()),
+    }
+    println!("File: {} bytes in {} chunk(s)", png.total_size(), png.len());
+    match png.ihdr() {
+        Ok(ihdr) => {
+            let color_type = ihdr.color_type().map_or("unknown".to_string(), |color_type| format!("{:?}", color_type));
+            println!("Image: {}x{}, bit depth {}, color type {}", ihdr.width(), ihdr.height(), ihdr.bit_depth(), color_type);
+        },
+        Err(e) => println!("Image: no valid IHDR chunk ({})", e),
+    }
+    if args.sniff {
+        // Numbered by their index in the file rather than among the selected chunks
+        let selected = chunk_filter(&args)?;
+        for (index, chunk) in png.chunks().iter().enumerate().filter(|(_, chunk)| selected(chunk)) {
+            println!("Chunk {} ({}) data: {}", index, chunk.chunk_type(), sniff::sniff(chunk.data()));
+        }
+    }
+    if args.groups {
+        for (group, types) in png.placement_report() {
+            let types: Vec<String> = types.iter().map(|chunk_type| chunk_type.to_string()).collect();
+            println!("{}: {}", group, types.join(" "));
+        }
+    }
+    if args.stats {
+        let (count, total_bytes) = png.idat_stats();
+        println!("IDAT: {} chunk(s), {} bytes of image data ({} bytes of chunk overhead)", count, total_bytes, count * 12);
+    }
+    for chunk in png.chunks().iter().filter(|chunk| &chunk.chunk_type().bytes() == b"tEXt") {
+        if let Ok((keyword, text)) = chunk.as_text() {
+            if keyword == BUILD_KEYWORD {
+                println!("Build stamp:\n{}", text);
+            }
+        }
+    }
+    if let Some(exif) = png.exif() {
+        let warning = if exif::has_byte_order_mark(exif) { "" } else { " (no TIFF byte-order mark)" };
+        println!("EXIF: {} bytes{}", exif.len(), warning);
+    }
+    match png.background() {
+        Ok(Some(background)) => println!("Background: {}", background),
+        Ok(None) => {},
+        Err(e) => println!("Background: invalid ({})", e),
+    }
+    println!("{}", render_png(listed, options.color));
+
+    Ok(())
+}
+
+/// Describes an error as a JSON object with its kind, message and context
+pub fn error_json(error: &Error) -> String {
+    let (kind, message, context) = if let Some(e) = error.downcast_ref::<ChunkDecodingError>() {
+        let mut context = e.context().to_vec();
+        if let Some(offset) = e.offset() {
+            context.push(("offset", offset.to_string()));
+        }
+        ("chunk_decoding", e.reason().to_string(), context)
+    } else if let Some(e) = error.downcast_ref::<PngError>() {
+        ("png", e.reason().to_string(), vec![])
+    } else if let Some(e) = error.downcast_ref::<io::Error>() {
+        ("io", e.to_string(), vec![])
+    } else {
+        ("other", error.to_string(), vec![])
+    };
+
+    let context: serde_json::Map<String, serde_json::Value> = context
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), value.into()))
+        .collect();
+    serde_json::json!({ "kind": kind, "message": message, "context": context }).to_string()
+}
+
+/// Runs the command on every file matching the glob pattern, printing the outcome for each file.
+/// A failure doesn't stop the batch, the failed files are reported once every file was processed
+fn for_each_match(pattern: &str, mut command: impl FnMut(String) -> Result<()>) -> Result<()> {
+    let filepaths: Vec<String> = glob::glob(pattern)?
+        .map(|path| Ok(path?.to_string_lossy().into_owned()))
+        .collect::<Result<_>>()?;
+    if filepaths.is_empty() {
+        return Err(format!("No file matches \"{}\"", pattern).into());
+    }
+
+    let mut failed = vec![];
+    for filepath in &filepaths {
+        match command(filepath.clone()) {
+            Ok(()) => println!("{}: ok", filepath),
+            Err(e) => {
+                println!("{}: failed ({})", filepath, e);
+                failed.push(filepath.as_str());
+            },
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} of {} file(s) failed: {}", failed.len(), filepaths.len(), failed.join(", ")).into())
+    }
+}
+
+/// Encodes the message into every file matching the pattern.
+/// The password is prompted for once, and the message can't come from stdin as it would only be read once
+fn encode_glob(args: EncodeArgs, options: &Options) -> Result<()> {
+    if args.message.as_deref() == Some("-") {
+        return Err("--glob can't read the message from stdin".into());
+    }
+    let password = PasswordArgs { password: read_password(args.password.clone())?, password_prompt: false };
+    let args = EncodeArgs { password, glob: false, ..args };
+    for_each_match(&args.filepath, |filepath| encode(EncodeArgs { filepath, ..args.clone() }, options))
+}
+
+/// Runs the command given on the command line
+pub fn run(args: Args) -> Result<()> {
+    let options = Options {
+        retries: args.retry,
+        deterministic: args.deterministic,
+        force_signature: args.force_signature,
+        max_file_size: args.max_file_size,
+        dry_run: args.dry_run,
+        color: use_color(args.color),
+        progress: !args.quiet,
+        max_chunks: args.max_chunks,
+    };
+
+    match args.command {
+        Commands::Encode(encode_args) if encode_args.glob => encode_glob(encode_args, &options)?,
+        Commands::Encode(encode_args) => encode(encode_args, &options)?,
+        Commands::Decode(decode_args) => decode(decode_args, args.format, &options)?,
+        Commands::Remove { filepath, chunk_type, glob: true, to_temp, force } => {
+            for_each_match(&filepath, |filepath| remove(filepath, chunk_type.clone(), to_temp, force, &options))?
+        },
+        Commands::Remove { filepath, chunk_type, glob: false, to_temp, force } => remove(filepath, chunk_type, to_temp, force, &options)?,
+        Commands::RemoveAt { filepath, index, force } => remove_at(filepath, index, force, &options)?,
+        Commands::Rename { filepath, old_type, new_type } => rename(filepath, old_type, new_type, &options)?,
+        Commands::Print(print_args) => print(print_args, args.format, &options)?,
+        Commands::Embed { filepath, chunk_type, input } => embed(filepath, chunk_type, input, &options)?,
+        Commands::Promote { filepath, chunk_type, keyword, all } => promote(filepath, chunk_type, keyword, all, &options)?,
+        Commands::Extract { filepath, chunk_type, output, index } => extract(filepath, chunk_type, output, index, &options)?,
+        Commands::Hexdump { filepath, chunk_type, index } => hexdump_chunk(filepath, chunk_type, index, &options)?,
+        Commands::Find { filepath, query, ignore_case } => find(filepath, query, ignore_case, &options)?,
+        Commands::Meta { filepath } => meta(filepath, &options)?,
+        Commands::Count { filepath } => count(filepath, &options)?,
+        Commands::List { filepath, type_only, sniff } => list(filepath, type_only, sniff, &options)?,
+        Commands::Lint { filepath, strict } => lint(filepath, strict, &options)?,
+        Commands::Strip { filepath, report } => strip(filepath, report, &options)?,
+        Commands::Optimize { filepath } => optimize(filepath, &options)?,
+        Commands::Dedup { filepath } => dedup(filepath, &options)?,
+        Commands::FixCase { filepath } => fix_case(filepath, &options)?,
+        Commands::ExtractExif { filepath, output } => extract_exif(filepath, output, &options)?,
+        Commands::Diff { left, right } => diff(left, right, &options)?,
+        Commands::RoundtripCheck { filepath } => roundtrip_check(filepath, &options)?,
+        Commands::Verify { filepath, only_invalid, strict } => verify(filepath, only_invalid, strict, &options)?,
+        Commands::StampBuild { filepath, commit, build_number, timestamp } => stamp_build(filepath, commit, build_number, timestamp, &options)?,
+        Commands::Fix { filepath } => fix(filepath, &options)?,
+        Commands::Dump { filepath } => dump(filepath, &options)?,
+        Commands::Load { json_filepath, filepath } => load(json_filepath, filepath, &options)?,
+    };
+
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::time::SystemTime;
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"first message".to_vec()),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"second message".to_vec()),
+        ])
+    }
+
+    #[test]
+    fn test_promote_round_trip() {
+        let mut png = testing_png();
+        let promoted = promote_chunks(&mut png, "ruSt", "Comment", false).unwrap();
+        assert_eq!(promoted, 1);
+
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        let text = png.chunk_by_type(ChunkType::from_str("tEXt").unwrap()).unwrap();
+        assert_eq!(text.data(), b"Comment\0first message");
+
+        let remaining = png.chunk_by_type(ChunkType::from_str("ruSt").unwrap()).unwrap();
+        assert_eq!(remaining.data(), b"second message");
+    }
+
+    #[test]
+    fn test_promote_all() {
+        let mut png = testing_png();
+        let promoted = promote_chunks(&mut png, "ruSt", "Comment", true).unwrap();
+        assert_eq!(promoted, 2);
+        assert!(png.chunk_by_type(ChunkType::from_str("ruSt").unwrap()).is_none());
+        assert_eq!(png.chunks().len(), 2);
+    }
+
+    #[test]
+    fn test_list_line() {
+        let chunk = Chunk::new(ChunkType::from_str("RuSt").unwrap(), b"This is where your secret message will be!".to_vec());
+        assert_eq!(list_line(&chunk, false), "RuSt  42  2882656334  critical");
+
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"%PDF-1.7".to_vec());
+        assert!(list_line(&chunk, false).ends_with("  ancillary"));
+        assert!(list_line(&chunk, true).ends_with("  ancillary  PDF"));
+    }
+
+    #[test]
+    fn test_render_png() {
+        let png = Png::from_chunks(vec![
+            Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13]),
+            Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"message".to_vec()),
+        ]);
+
+        let plain = render_png(&png, use_color(ColorChoice::Never));
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(plain, png.to_string());
+
+        let colored = render_png(&png, use_color(ColorChoice::Always));
+        assert!(colored.contains('\x1b'));
+        assert_ne!(paint(png.chunks()[0].chunk_type(), "IHDR", true), paint(png.chunks()[1].chunk_type(), "IHDR", true));
+    }
+
+    #[test]
+    fn test_hexdump() {
+        let dump = hexdump(b"Hello, hexdump world!\n");
+        let expected = "00000000: 4865 6c6c 6f2c 2068 6578 6475 6d70 2077  Hello, hexdump w\n\
+                        00000010: 6f72 6c64 210a                           orld!.\n";
+        assert_eq!(dump, expected);
+    }
+
+    #[test]
+    fn test_hexdump_missing_chunk() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "hexdump", filepath, "ruSt", "--index", "1"])).unwrap();
+
+        let error = run(Args::parse_from(["pngme", "hexdump", filepath, "ruSt", "--index", "2"])).unwrap_err();
+        assert_eq!(error.to_string(), "No chunk found with type \"ruSt\" at index 2");
+        assert!(run(Args::parse_from(["pngme", "hexdump", filepath, "noNe"])).is_err());
+    }
+
+    #[test]
+    fn test_hexdump_empty() {
+        assert_eq!(hexdump(&[]), "");
+    }
+
+    #[test]
+    fn test_write_temp_png() {
+        let mut png = testing_png();
+        let options = Options::default();
+        let first = write_temp_png(&mut png, &options).unwrap();
+        let second = write_temp_png(&mut png, &options).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read(&first).unwrap(), png.as_bytes());
+
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn test_error_json() {
+        let mut bytes = testing_png().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+
+        let error = Png::try_from(bytes.as_slice()).err().unwrap();
+        let json: serde_json::Value = serde_json::from_str(&error_json(&error)).unwrap();
+        assert_eq!(json["kind"], "chunk_decoding");
+        assert!(json["message"].as_str().unwrap().starts_with("CRC mismatch"));
+        assert_eq!(json["context"]["chunk_type"], "ruSt");
+        assert!(json["context"]["expected_crc"].is_string());
+        assert!(json["context"]["actual_crc"].is_string());
+        assert_eq!(json["context"]["offset"], "33");
+
+        let error: Error = "plain \"quoted\" error\n".into();
+        let json: serde_json::Value = serde_json::from_str(&error_json(&error)).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "other", "message": "plain \"quoted\" error\n", "context": {} }));
+    }
+
+    #[test]
+    fn test_strip_report() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str("tEXt").unwrap(), b"Comment\0text".to_vec()));
+        png.append_chunk(Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]));
+        let size_before = png.as_bytes().len();
+        let removed = png.retain_critical();
+        let report = strip_report(&removed, size_before, png.as_bytes().len());
+
+        let expected = "Type     Count       Bytes\n\
+                        ruSt         2          51\n\
+                        tEXt         1          24\n\
+                        Total        3          75\n\
+                        Size: 95 -> 20 bytes\n";
+        assert_eq!(report, expected);
+    }
+
+    #[test]
+    fn test_verify_report() {
+        let mut bytes = testing_png().as_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        let (png, errors) = Png::try_from_lenient(&bytes);
+
+        let report = verify_report(&png, &errors, false);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0], "OK      chunk 0 (ruSt)");
+        assert!(report[1].starts_with("INVALID chunk 1 (ruSt) at offset 33: CRC mismatch"));
+
+        let report = verify_report(&png, &errors, true);
+        assert_eq!(report.len(), 1);
+        assert!(report[0].starts_with("INVALID chunk 1"));
+    }
+
+    #[test]
+    fn test_verify_corrupted_crc() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "verify", filepath])).unwrap();
+
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, bytes).unwrap();
+
+        let error = run(Args::parse_from(["pngme", "verify", filepath])).unwrap_err();
+        assert!(error.to_string().contains("Found 1 problem(s)"));
+    }
+
+    #[test]
+    fn test_strict_layout() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "verify", filepath])).unwrap();
+        run(Args::parse_from(["pngme", "print", filepath])).unwrap();
+
+        let error = run(Args::parse_from(["pngme", "verify", "--strict", filepath])).unwrap_err();
+        assert!(error.to_string().contains("Found 2 problem(s)"));
+        let error = run(Args::parse_from(["pngme", "print", "--strict", filepath])).unwrap_err();
+        assert!(error.to_string().contains("missing IHDR chunk, missing IEND chunk"));
+    }
+
+    #[test]
+    fn test_print_reports_offset() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, bytes).unwrap();
+
+        let error = run(Args::parse_from(["pngme", "print", filepath])).unwrap_err();
+        assert!(error.to_string().starts_with("Bad chunk at offset 33: CRC mismatch"));
+    }
+
+    #[test]
+    fn test_select_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str("IDAT").unwrap(), b"image data".to_vec()));
+        png.append_chunk(Chunk::new(ChunkType::from_str("teSt").unwrap(), b"other".to_vec()));
+        let select = |filters: &[&str]| {
+            let args = Args::parse_from(["pngme", "print", "file.png"].iter().chain(filters));
+            let Commands::Print(print_args) = args.command else { unreachable!() };
+            select_chunks(&png, &print_args)
+                .unwrap()
+                .map(|png| png.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect::<Vec<String>>())
+                .unwrap_or_default()
+        };
+
+        assert_eq!(select(&["--type", "ruSt"]), ["ruSt", "ruSt"]);
+        assert_eq!(select(&["--type", "ruSt", "--type", "IDAT"]), ["ruSt", "ruSt", "IDAT"]);
+        assert_eq!(select(&["--critical-only"]), ["IDAT"]);
+        assert_eq!(select(&["--ancillary-only"]), ["ruSt", "ruSt", "teSt"]);
+        assert_eq!(select(&["--type", "ruSt", "--critical-only"]), Vec::<String>::new());
+        assert!(select(&[]).is_empty());
+
+        let (path, _) = old_png_file();
+        run(Args::parse_from(["pngme", "print", path.to_str().unwrap(), "--type", "ruSt", "--sniff"])).unwrap();
+        assert!(run(Args::parse_from(["pngme", "print", path.to_str().unwrap(), "--type", "bad"])).is_err());
+    }
+
+    #[test]
+    fn test_max_chunks() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "print", filepath, "--max-chunks", "2"])).unwrap();
+
+        let error = run(Args::parse_from(["pngme", "print", filepath, "--max-chunks", "1"])).unwrap_err();
+        assert!(error.to_string().contains("too many chunks (the limit is 1)"));
+        assert!(run(Args::parse_from(["pngme", "encode", filepath, "ruSt", "third", "--max-chunks", "1"])).is_err());
+
+        let error = run(Args::parse_from(["pngme", "print", filepath, "--force-signature", "--max-chunks", "1"])).unwrap_err();
+        assert!(error.to_string().contains("too many chunks (the limit is 1)"));
+        assert!(run(Args::parse_from(["pngme", "verify", filepath, "--max-chunks", "1"])).is_err());
+        assert!(run(Args::parse_from(["pngme", "fix", filepath, "--max-chunks", "1"])).is_err());
+        assert_eq!(Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_fix_corrupted_crc() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        let mut bytes = fs::read(&path).unwrap();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        assert!(Png::try_from(bytes.as_slice()).is_err());
+
+        run(Args::parse_from(["pngme", "fix", filepath])).unwrap();
+        assert_eq!(Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap().as_bytes(), testing_png().as_bytes());
+    }
+
+    #[test]
+    fn test_fix_truncated_file() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        let bytes = fs::read(&path).unwrap();
+        fs::write(&path, &bytes[..bytes.len() - 2]).unwrap();
+
+        assert!(run(Args::parse_from(["pngme", "fix", filepath])).is_err());
+        assert_eq!(fs::read(&path).unwrap(), bytes[..bytes.len() - 2]);
+    }
+
+    #[test]
+    fn test_strip_two_ancillary_chunks() {
+        let mut png = testing_png();
+        png.insert_chunk(0, Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13])).unwrap();
+        png.append_chunk(Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]));
+        let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+        fs::write(&path, png.as_bytes()).unwrap();
+
+        run(Args::parse_from(["pngme", "strip", path.to_str().unwrap()])).unwrap();
+
+        let stripped = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        let types: Vec<&str> = stripped.chunks().iter().map(|chunk| chunk.chunk_type().as_str()).collect();
+        assert_eq!(types, ["IHDR", "IEND"]);
+    }
+
+    #[test]
+    fn test_find() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "find", filepath, "SECOND", "--ignore-case"])).unwrap();
+        run(Args::parse_from(["pngme", "find", filepath, "absent"])).unwrap();
+        assert!(run(Args::parse_from(["pngme", "find", filepath, ""])).is_err());
+    }
+
+    #[test]
+    fn test_extract_binary_chunk() {
+        let data: Vec<u8> = (0..=255).rev().collect();
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str("biNy").unwrap(), data.clone()));
+        let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+        fs::write(&path, png.as_bytes()).unwrap();
+        let output = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+
+        run(Args::parse_from(["pngme", "extract", path.to_str().unwrap(), "biNy", output.to_str().unwrap()])).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), data);
+
+        run(Args::parse_from(["pngme", "extract", path.to_str().unwrap(), "ruSt", output.to_str().unwrap(), "--index", "1"])).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), b"second message");
+
+        assert!(run(Args::parse_from(["pngme", "extract", path.to_str().unwrap(), "ruSt", output.to_str().unwrap(), "--index", "2"])).is_err());
+    }
+
+    #[test]
+    fn test_embed_extract_round_trip() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        let data: Vec<u8> = (0..4096u32).map(|i| (i * 7 % 256) as u8).collect();
+        let input = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+        fs::write(&input, &data).unwrap();
+        let output = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+
+        run(Args::parse_from(["pngme", "embed", filepath, "blOb", input.to_str().unwrap()])).unwrap();
+        run(Args::parse_from(["pngme", "extract", filepath, "blOb", output.to_str().unwrap()])).unwrap();
+        assert_eq!(fs::read(&output).unwrap(), data);
+    }
+
+    #[test]
+    fn test_diff_line() {
+        let left = testing_png();
+        let mut right = testing_png();
+        right.chunk_at_mut(1).unwrap().set_data(b"changed".to_vec());
+        right.append_chunk(Chunk::new(ChunkType::from_str("neWs").unwrap(), b"new".to_vec()));
+
+        let lines: Vec<String> = left.diff(&right).iter().map(|diff| diff_line(&left, &right, diff)).collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("~ chunk 1 -> 1 \"ruSt\" (14 -> 7 bytes, CRC "));
+        assert_eq!(lines[1], "+ chunk 2 \"neWs\" (3 bytes)");
+    }
+
+    #[test]
+    fn test_build_stamp() {
+        let stamp = build_stamp(Some("3f2a9c1".to_string()), None, Some("2024-05-01T12:00:00Z".to_string())).unwrap();
+        assert_eq!(stamp, "commit: 3f2a9c1\ntimestamp: 2024-05-01T12:00:00Z");
+        assert!(build_stamp(None, None, None).is_err());
+    }
+
+    #[test]
+    fn test_max_file_size() {
+        let png = testing_png();
+        let size = png.as_bytes().len() as u64;
+
+        let options = Options { max_file_size: Some(size), ..Options::default() };
+        assert!(prepare_png(&png, &options).is_ok());
+
+        let options = Options { max_file_size: Some(size - 1), ..options };
+        let error = prepare_png(&png, &options).err().unwrap();
+        assert!(error.to_string().contains(&format!("would be {} bytes", size)));
+    }
+
+    #[test]
+    fn test_read_file_range() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"headerMIDDLEtrailer").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        assert_eq!(read_file_range(path, 6, Some(6)).unwrap(), b"MIDDLE");
+        assert_eq!(read_file_range(path, 12, None).unwrap(), b"trailer");
+        assert_eq!(read_file_range(path, 19, None).unwrap(), b"");
+    }
+
+    #[test]
+    fn test_read_file_range_out_of_bounds() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"headerMIDDLEtrailer").unwrap();
+        let path = file.path().to_str().unwrap();
+
+        assert!(read_file_range(path, 12, Some(8)).is_err());
+        assert!(read_file_range(path, 20, None).is_err());
+        assert!(read_file_range(path, u64::MAX, Some(1)).is_err());
+    }
+
+    #[test]
+    fn test_message_bytes_from_stdin() {
+        let stdin = io::Cursor::new(vec![0u8, 159, 146, 150, b'\n']);
+        let data = message_bytes("-".to_string(), stdin).unwrap();
+        assert_eq!(data, [0, 159, 146, 150, b'\n']);
+
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), data);
+        assert_eq!(chunk.data(), [0, 159, 146, 150, b'\n']);
+    }
+
+    #[test]
+    fn test_message_bytes_from_argument() {
+        let stdin = io::Cursor::new(b"unused".to_vec());
+        assert_eq!(message_bytes("hello".to_string(), stdin).unwrap(), b"hello");
+    }
+
+    #[test]
+    fn test_encode_binary_message_file() {
+        let message: Vec<u8> = (0..=255).collect();
+        let mut message_file = tempfile::NamedTempFile::new().unwrap();
+        message_file.write_all(&message).unwrap();
+
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        let message_path = message_file.path().to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "biNy", "--message-file", message_path])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        assert_eq!(png.chunk_by_type(ChunkType::from_str("biNy").unwrap()).unwrap().data(), message);
+    }
+
+    #[test]
+    fn test_message_and_message_file_conflict() {
+        let error = Args::try_parse_from(["pngme", "encode", "image.png", "ruSt", "message", "--message-file", "message.bin"]).unwrap_err();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+    }
+
+    #[test]
+    fn test_remove_iend_requires_force() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "IEND", ""])).unwrap();
+
+        let error = run(Args::parse_from(["pngme", "remove", filepath, "IEND"])).unwrap_err();
+        assert!(error.to_string().contains("refusing to remove critical chunk IEND"));
+        assert!(Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap().chunk_by_type(ChunkType::from_str("IEND").unwrap()).is_some());
+
+        run(Args::parse_from(["pngme", "remove", filepath, "IEND", "--force"])).unwrap();
+        assert!(Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap().chunk_by_type(ChunkType::from_str("IEND").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_encode_hex_message() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "biNy", "00FF7f80", "--hex"])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        let chunk = png.chunk_by_type(ChunkType::from_str("biNy").unwrap()).unwrap();
+        assert_eq!(chunk.data(), [0x00, 0xff, 0x7f, 0x80]);
+        run(Args::parse_from(["pngme", "decode", filepath, "biNy", "--hex"])).unwrap();
+
+        let error = run(Args::parse_from(["pngme", "encode", filepath, "biNy", "abc", "--hex"])).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid hex message: hex data has an odd length (3)");
+        let error = run(Args::parse_from(["pngme", "encode", filepath, "biNy", "zz", "--hex"])).unwrap_err();
+        assert_eq!(error.to_string(), "Invalid hex message: invalid hex data at character 0");
+        assert_eq!(Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_encode_base64_message() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "biNy", "//4AgA==", "--base64"])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        let chunk = png.chunk_by_type(ChunkType::from_str("biNy").unwrap()).unwrap();
+        assert_eq!(chunk.data(), [0xff, 0xfe, 0x00, 0x80]);
+        assert_eq!(chunk.data_as_base64(), "//4AgA==");
+
+        assert!(run(Args::parse_from(["pngme", "encode", filepath, "biNy", "not base64!", "--base64"])).is_err());
+    }
+
+    #[test]
+    fn test_split_message_round_trip() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        let message = "a message longer than the chunk size";
+        run(Args::parse_from(["pngme", "encode", filepath, "spLt", message, "--chunk-size", "8", "--compress"])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        let chunks = png.chunks_by_type(ChunkType::from_str("spLt").unwrap());
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|chunk| chunk.data().len() <= 8));
+
+        let data: Vec<u8> = chunks.iter().flat_map(|chunk| chunk.data()).copied().collect();
+        assert_eq!(unseal(&data, true, None).unwrap(), message.as_bytes());
+
+        run(Args::parse_from(["pngme", "decode", filepath, "spLt", "--join", "--decompress"])).unwrap();
+    }
+
+    #[test]
+    fn test_encode_auto_type() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "--auto-type", "aGVsbG8=", "--base64"])).unwrap();
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        assert_eq!(png.chunks().last().unwrap().data(), b"hello");
+
+        let message_path = path.with_extension("message");
+        fs::write(&message_path, "from file").unwrap();
+        let message_file = message_path.to_str().unwrap();
+        let error = run(Args::parse_from(["pngme", "encode", filepath, "--auto-type", "foo", "--message-file", message_file])).unwrap_err();
+        assert_eq!(error.to_string(), "A message can't be given with --message-file or --embed-files");
+        assert_eq!(Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap().len(), 3);
+
+        run(Args::parse_from(["pngme", "encode", filepath, "--auto-type", "--message-file", message_file])).unwrap();
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        assert_eq!(png.chunks().last().unwrap().data(), b"from file");
+        assert!(Args::try_parse_from(["pngme", "encode", filepath, "--auto-type", "--message-file", message_file, "--hex"]).is_err());
+        fs::remove_file(message_path).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_keeps_file() {
+        let (path, modified) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "drYr", "not written", "--dry-run"])).unwrap();
+        run(Args::parse_from(["pngme", "remove", filepath, "ruSt", "--dry-run"])).unwrap();
+        for command in [
+            vec!["strip", filepath],
+            vec!["fix", filepath],
+            vec!["promote", filepath, "ruSt", "Comment", "--all"],
+            vec!["stamp-build", filepath, "--commit", "abc123"],
+        ] {
+            run(Args::parse_from(["pngme"].into_iter().chain(command).chain(["--dry-run"]))).unwrap();
+        }
+
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        assert_eq!(fs::read(&path).unwrap(), testing_png().as_bytes());
+
+        let json_path = path.with_extension("json");
+        let new_path = path.with_extension("new.png");
+        fs::write(&json_path, json::dump(&testing_png()).unwrap()).unwrap();
+        run(Args::parse_from(["pngme", "load", json_path.to_str().unwrap(), new_path.to_str().unwrap(), "--dry-run"])).unwrap();
+        assert!(!new_path.exists());
+        fs::remove_file(json_path).unwrap();
+    }
+
+    #[test]
+    fn test_rename() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "rename", filepath, "ruSt", "teSt"])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        assert_eq!(png.chunks()[0].chunk_type().to_string(), "teSt");
+        assert_eq!(png.chunks()[0].data(), b"first message");
+        assert_eq!(png.chunks()[1].chunk_type().to_string(), "ruSt");
+
+        assert!(run(Args::parse_from(["pngme", "rename", filepath, "noNe", "teSt"])).is_err());
+    }
+
+    #[test]
+    fn test_dedup() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "ruSt", "first message"])).unwrap();
+        run(Args::parse_from(["pngme", "dedup", filepath])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        assert_eq!(png.chunks(), testing_png().chunks());
+    }
+
+    #[test]
+    fn test_read_png_with_progress() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        let bar = ProgressBar::hidden();
+
+        let png = read_png_with_progress(filepath, Png::DEFAULT_MAX_CHUNKS, bar.clone()).unwrap();
+        assert_eq!(png.chunks(), testing_png().chunks());
+        assert_eq!(bar.position(), fs::metadata(&path).unwrap().len());
+        assert!(bar.is_finished());
+    }
+
+    #[test]
+    fn test_glob_batch() {
+        let directory = tempfile::tempdir().unwrap();
+        for name in ["first.png", "second.png"] {
+            fs::write(directory.path().join(name), testing_png().as_bytes()).unwrap();
+        }
+        let pattern = directory.path().join("*.png");
+        let pattern = pattern.to_str().unwrap();
+
+        run(Args::parse_from(["pngme", "encode", pattern, "taGs", "©2024", "--glob"])).unwrap();
+        for name in ["first.png", "second.png"] {
+            let png = Png::try_from(fs::read(directory.path().join(name)).unwrap().as_slice()).unwrap();
+            assert_eq!(png.chunk_by_type(ChunkType::from_str("taGs").unwrap()).unwrap().data(), "©2024".as_bytes());
+        }
+
+        // A failure on one file doesn't stop the others
+        fs::write(directory.path().join("first.png"), b"not a PNG").unwrap();
+        let error = run(Args::parse_from(["pngme", "remove", pattern, "taGs", "--glob"])).unwrap_err();
+        assert!(error.to_string().starts_with("1 of 2 file(s) failed"));
+        assert!(error.to_string().ends_with("first.png"));
+        let png = Png::try_from(fs::read(directory.path().join("second.png")).unwrap().as_slice()).unwrap();
+        assert_eq!(png.chunks(), testing_png().chunks());
+
+        let pattern = directory.path().join("*.jpg");
+        assert!(run(Args::parse_from(["pngme", "remove", pattern.to_str().unwrap(), "taGs", "--glob"])).is_err());
+    }
+
+    #[test]
+    fn test_remove_at() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "remove-at", filepath, "0"])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        assert_eq!(png.len(), 1);
+        assert_eq!(png.chunks()[0].data(), b"second message");
+
+        assert!(run(Args::parse_from(["pngme", "remove-at", filepath, "1"])).is_err());
+    }
+
+    #[test]
+    fn test_encode_text() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "--text", "Author=Jane=Doe"])).unwrap();
+        run(Args::parse_from(["pngme", "decode", filepath, "--text"])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        let chunk = png.chunk_by_type(ChunkType::from_str("tEXt").unwrap()).unwrap();
+        assert_eq!(chunk.as_text().unwrap(), ("Author".to_string(), "Jane=Doe".to_string()));
+
+        assert!(run(Args::parse_from(["pngme", "encode", filepath, "--text", "no separator"])).is_err());
+        assert!(run(Args::parse_from(["pngme", "encode", filepath, "--text", &format!("{}=value", "k".repeat(80))])).is_err());
+    }
+
+    #[test]
+    fn test_seal_round_trip() {
+        let message = "a 5 KB message, ".repeat(320).into_bytes();
+        let options = Options::default();
+        let sealed = seal(message.clone(), true, None, &options).unwrap();
+        assert!(sealed.len() < message.len());
+        assert_eq!(unseal(&sealed, true, None).unwrap(), message);
+        assert_eq!(unseal(&seal(message.clone(), false, None, &options).unwrap(), false, None).unwrap(), message);
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview(b"hello world", None), "\"hello world\"");
+        assert_eq!(preview(b"hello world", Some(11)), "\"hello world\"");
+        assert_eq!(preview(b"hello world", Some(5)), "\"hello\" (truncated to 5 of 11 bytes)");
+        assert_eq!(preview("héllo".as_bytes(), Some(2)), "\"h\" (truncated to 1 of 6 bytes)");
+        assert_eq!(preview(&[0xff, 0x00, 0x80], None), "(base64) /wCA");
+        assert_eq!(preview(&[0xff, 0x00, 0x80], Some(2)), "(base64) /wA= (truncated to 2 of 3 bytes)");
+        assert_eq!(hex_preview(&[0xff, 0x00, 0x80], None), "ff0080");
+        assert_eq!(hex_preview(&[0xff, 0x00, 0x80], Some(1)), "ff (truncated to 1 of 3 bytes)");
+    }
+
+    #[test]
+    fn test_rust_literal() {
+        assert_eq!(rust_literal("ruSt_data", b"hi!"), "const RUST_DATA: &[u8] = &[\n    0x68, 0x69, 0x21,\n];\n");
+        assert_eq!(rust_literal("empty", b""), "const EMPTY: &[u8] = &[\n];\n");
+    }
+
+    #[test]
+    fn test_c_array() {
+        assert_eq!(c_array("ruSt_data", b"hi!"), "unsigned char rust_data[] = {\n    0x68, 0x69, 0x21,\n};\nunsigned int rust_data_len = 3;\n");
+    }
+
+    #[test]
+    fn test_byte_rows_wrapping() {
+        let rows = byte_rows(&[0xAB; 25]);
+        let lines: Vec<&str> = rows.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], format!("    {}", ["0xab,"; 12].join(" ")));
+        assert_eq!(lines[2], "    0xab,");
+    }
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(first_difference(b"abc", b"abc"), None);
+        assert_eq!(first_difference(b"abc", b"abd"), Some(2));
+        assert_eq!(first_difference(b"abc", b"ab"), Some(2));
+        assert_eq!(first_difference(b"", b"a"), Some(0));
+    }
+
+    #[test]
+    fn test_roundtrip_check() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "roundtrip-check", filepath])).unwrap();
+
+        let mut bytes = testing_png().as_bytes();
+        let size = bytes.len();
+        bytes.extend_from_slice(b"extra");
+        fs::write(&path, &bytes).unwrap();
+        let error = run(Args::parse_from(["pngme", "roundtrip-check", filepath])).unwrap_err();
+        assert!(error.to_string().contains(&format!("differs at offset {size} (read {} bytes, wrote {size} bytes)", size + 5)));
+
+        bytes.truncate(size);
+        bytes[size - 1] ^= 1;
+        fs::write(&path, &bytes).unwrap();
+        let error = run(Args::parse_from(["pngme", "roundtrip-check", filepath])).unwrap_err();
+        assert!(error.to_string().contains(&format!("differs at offset {}", size - 1)));
+    }
+
+    #[test]
+    fn test_fixture_round_trip() {
+        let bytes = include_bytes!("../dice.png");
+        let png = Png::try_from(&bytes[..]).unwrap();
+        assert_eq!(first_difference(bytes, &png.as_bytes()), None);
+    }
+
+    /// Writes the testing PNG to a temporary file last modified a long time ago
+    fn old_png_file() -> (tempfile::TempPath, SystemTime) {
+        let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+        fs::write(&path, testing_png().as_bytes()).unwrap();
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+        File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        (path, modified)
+    }
+
+    #[test]
+    fn test_unchanged_write_keeps_mtime() {
+        let (path, modified) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "ruSt", "third message", "--if-missing"])).unwrap();
+
+        assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        assert_eq!(fs::read(&path).unwrap(), testing_png().as_bytes());
+    }
+
+    #[test]
+    fn test_holds_png() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        let png = testing_png();
+        let size = png.total_size() as u64;
+        assert!(holds_png(filepath, &png, size));
+
+        let mut edited = testing_png();
+        edited.remove_chunk_by_index(0).unwrap();
+        edited.insert_chunk(0, Chunk::new(ChunkType::from_str("ruSt").unwrap(), b"first massage".to_vec())).unwrap();
+        assert_eq!(edited.total_size() as u64, size);
+        assert!(!holds_png(filepath, &edited, size));
+        assert!(!holds_png("missing.png", &png, size));
+    }
+
+    #[test]
+    fn test_changed_write_updates_file() {
+        let (path, modified) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "ruSt", "third message"])).unwrap();
+
+        assert_ne!(fs::metadata(&path).unwrap().modified().unwrap(), modified);
+        assert_eq!(read_png(filepath, &Options::default()).unwrap().chunks().len(), 3);
+    }
+
+    #[test]
+    fn test_encrypted_encode_decode() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        run(Args::parse_from(["pngme", "encode", filepath, "seCr", "my secret note", "--password", "hunter2"])).unwrap();
+
+        let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+        let chunks = png.chunks_by_type(ChunkType::from_str("seCr").unwrap());
+        assert_eq!(unseal(chunks[0].data(), false, Some("hunter2")).unwrap(), b"my secret note");
+
+        let error = run(Args::parse_from(["pngme", "decode", filepath, "seCr", "--password", "hunter3"])).unwrap_err();
+        assert!(error.to_string().contains("decryption failed (wrong password?)"));
+    }
+
+    #[test]
+    fn test_deterministic_encryption() {
+        let encode = || {
+            let (path, _) = old_png_file();
+            let filepath = path.to_str().unwrap();
+            run(Args::parse_from(["pngme", "--deterministic", "encode", filepath, "seCr", "note", "--password", "hunter2"])).unwrap();
+            fs::read(&path).unwrap()
+        };
+        assert_eq!(encode(), encode());
+    }
+
+    #[test]
+    fn test_is_lock_error() {
+        let (path, _) = old_png_file();
+        let filepath = path.to_str().unwrap();
+        assert!(is_lock_error(&io::Error::from(io::ErrorKind::PermissionDenied), filepath));
+        assert!(!is_lock_error(&io::Error::from(io::ErrorKind::NotFound), filepath));
+
+        let mut permissions = fs::metadata(&path).unwrap().permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&path, permissions).unwrap();
+        assert!(!is_lock_error(&io::Error::from(io::ErrorKind::PermissionDenied), filepath));
+    }
+
+    #[test]
+    fn test_check_safe_to_copy() {
+        assert!(check_safe_to_copy(&ChunkType::from_str("ruSt").unwrap()).is_ok());
+        assert!(check_safe_to_copy(&ChunkType::from_str("ruST").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_promote_text_chunk() {
+        let mut png = testing_png();
+        assert!(promote_chunks(&mut png, "tEXt", "Comment", true).is_err());
+    }
+
+    #[test]
+    fn test_promote_errors() {
+        let mut png = testing_png();
+        png.insert_chunk(0, Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 13])).unwrap();
+        let error = promote_chunks(&mut png, "IHDR", "Comment", false).unwrap_err();
+        assert!(error.to_string().contains("refusing to remove critical chunk IHDR"));
+
+        assert!(promote_chunks(&mut png, "ruSt", " bad keyword", false).is_err());
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(promote_chunks(&mut png, "noNe", "Comment", true).unwrap(), 0);
+    }
+}
@@ -0,0 +1,104 @@
+use crate::error::ReasonError;
+use crate::Result;
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+use sha2::Sha256;
+
+/// Size of the random salt used to derive the key from the password
+const SALT_LENGTH: usize = 16;
+/// Size of the AES-GCM nonce
+const NONCE_LENGTH: usize = 12;
+/// Number of PBKDF2-HMAC-SHA256 iterations used to derive the key
+#[cfg(not(test))]
+const ITERATIONS: u32 = 600_000;
+/// Fewer iterations in unit tests, which would take seconds each in debug builds
+#[cfg(test)]
+const ITERATIONS: u32 = 1_000;
+/// Prefix of the encryption and decryption errors
+const ERROR_PREFIX: &str = "Can't process encrypted data";
+
+/// Encrypts the message with AES-256-GCM, using a key derived from the password with PBKDF2.
+/// The result holds the salt, the nonce and the ciphertext (followed by its authentication tag)
+pub fn encrypt(message: &[u8], password: &str) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LENGTH];
+    let mut nonce = [0u8; NONCE_LENGTH];
+    getrandom::fill(&mut salt).map_err(|e| ReasonError::boxed(ERROR_PREFIX, format!("can't generate a salt ({e})")))?;
+    getrandom::fill(&mut nonce).map_err(|e| ReasonError::boxed(ERROR_PREFIX, format!("can't generate a nonce ({e})")))?;
+    encrypt_with(message, password, salt, nonce)
+}
+
+/// Encrypts like `encrypt` but with a fixed (zero) salt and nonce, so the same message and password
+/// always give the same bytes. This weakens the encryption: every message encrypted with the same
+/// password shares the key and the nonce, which lets anyone holding two of them recover the XOR of
+/// their plaintexts and forge new ones. Only use it when byte-stable output matters more than secrecy
+pub fn encrypt_deterministic(message: &[u8], password: &str) -> Result<Vec<u8>> {
+    encrypt_with(message, password, [0; SALT_LENGTH], [0; NONCE_LENGTH])
+}
+
+fn encrypt_with(message: &[u8], password: &str, salt: [u8; SALT_LENGTH], nonce: [u8; NONCE_LENGTH]) -> Result<Vec<u8>> {
+    let ciphertext = cipher(password, &salt)
+        .encrypt(&Nonce::from(nonce), message)
+        .map_err(|_| ReasonError::boxed(ERROR_PREFIX, "encryption failed".to_string()))?;
+    Ok([&salt[..], &nonce, &ciphertext].concat())
+}
+
+/// Decrypts bytes produced by `encrypt`, failing if the password is wrong or the bytes were altered
+pub fn decrypt(bytes: &[u8], password: &str) -> Result<Vec<u8>> {
+    if bytes.len() < SALT_LENGTH + NONCE_LENGTH {
+        return Err(ReasonError::boxed(ERROR_PREFIX, format!(
+            "encrypted data has size {} (expected at least {})",
+            bytes.len(),
+            SALT_LENGTH + NONCE_LENGTH
+        )));
+    }
+    let (salt, rest) = bytes.split_at(SALT_LENGTH);
+    let (nonce, ciphertext) = rest.split_at(NONCE_LENGTH);
+    let nonce: [u8; NONCE_LENGTH] = nonce.try_into()?;
+
+    cipher(password, salt)
+        .decrypt(&Nonce::from(nonce), ciphertext)
+        .map_err(|_| ReasonError::boxed(ERROR_PREFIX, "decryption failed (wrong password?)".to_string()).into())
+}
+
+fn cipher(password: &str, salt: &[u8]) -> Aes256Gcm {
+    let key: [u8; 32] = pbkdf2::pbkdf2_hmac_array::<Sha256, 32>(password.as_bytes(), salt, ITERATIONS);
+    Aes256Gcm::new(&Key::<Aes256Gcm>::from(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let encrypted = encrypt(b"my secret note", "hunter2").unwrap();
+        assert_ne!(&encrypted[SALT_LENGTH + NONCE_LENGTH..], b"my secret note");
+        assert_eq!(decrypt(&encrypted, "hunter2").unwrap(), b"my secret note");
+    }
+
+    #[test]
+    fn test_wrong_password() {
+        let encrypted = encrypt(b"my secret note", "hunter2").unwrap();
+        let error = decrypt(&encrypted, "hunter3").unwrap_err();
+        assert!(error.to_string().contains("decryption failed (wrong password?)"));
+    }
+
+    #[test]
+    fn test_random_salt_and_nonce() {
+        assert_ne!(encrypt(b"my secret note", "hunter2").unwrap(), encrypt(b"my secret note", "hunter2").unwrap());
+    }
+
+    #[test]
+    fn test_deterministic_round_trip() {
+        let encrypted = encrypt_deterministic(b"my secret note", "hunter2").unwrap();
+        assert_eq!(encrypted, encrypt_deterministic(b"my secret note", "hunter2").unwrap());
+        assert_eq!(decrypt(&encrypted, "hunter2").unwrap(), b"my secret note");
+        assert!(decrypt(&encrypted, "hunter3").is_err());
+    }
+
+    #[test]
+    fn test_truncated_data() {
+        assert!(decrypt(&[0; SALT_LENGTH], "hunter2").is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use std::fmt;
+
+/// Error described by a reason, displayed after a prefix naming the operation that failed
+#[derive(Debug)]
+pub struct ReasonError {
+    prefix: &'static str,
+    reason: String,
+}
+impl ReasonError {
+    pub(crate) fn boxed(prefix: &'static str, reason: String) -> Box<Self> {
+        Box::new(Self { prefix, reason })
+    }
+
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+}
+impl fmt::Display for ReasonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.prefix, self.reason)
+    }
+}
+impl std::error::Error for ReasonError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reason_error() {
+        let error = ReasonError::boxed("Can't do it", "no reason".to_string());
+        assert_eq!(error.reason(), "no reason");
+        assert_eq!(error.to_string(), "Can't do it: no reason");
+    }
+}
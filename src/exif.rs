@@ -0,0 +1,26 @@
+/// Byte-order marks starting a TIFF header, which the EXIF data of an eXIf chunk must start with
+const BYTE_ORDER_MARKS: [&[u8; 4]; 2] = [b"II*\0", b"MM\0*"];
+
+/// Returns whether the data starts with a little-endian (`II`) or big-endian (`MM`) TIFF header
+pub fn has_byte_order_mark(data: &[u8]) -> bool {
+    BYTE_ORDER_MARKS.iter().any(|mark| data.starts_with(&mark[..]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_byte_order_marks() {
+        assert!(has_byte_order_mark(b"II*\0\x08\0\0\0"));
+        assert!(has_byte_order_mark(b"MM\0*\0\0\0\x08"));
+    }
+
+    #[test]
+    fn test_missing_byte_order_mark() {
+        assert!(!has_byte_order_mark(b""));
+        assert!(!has_byte_order_mark(b"II"));
+        assert!(!has_byte_order_mark(b"Exif\0\0II*\0"));
+        assert!(!has_byte_order_mark(b"IM*\0"));
+    }
+}
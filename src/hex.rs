@@ -0,0 +1,41 @@
+/// Encodes the bytes as a lowercase hex string, two digits per byte
+pub fn encode(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// Decodes a hex string of either case into bytes.
+/// Fails on an odd number of digits or on any character that isn't a hex digit
+pub fn decode(hex: &str) -> Result<Vec<u8>, String> {
+    if !hex.len().is_multiple_of(2) {
+        return Err(format!("hex data has an odd length ({})", hex.len()));
+    }
+    if let Some(index) = hex.bytes().position(|byte| !byte.is_ascii_hexdigit()) {
+        return Err(format!("invalid hex data at character {index}"));
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(&hex[index..index + 2], 16).map_err(|e| e.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(encode(&[0x00, 0xab, 0x10]), "00ab10");
+        assert_eq!(decode(&encode(&bytes)).unwrap(), bytes);
+        assert_eq!(decode("DEADbeef").unwrap(), [0xde, 0xad, 0xbe, 0xef]);
+        assert!(decode("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_hex() {
+        assert_eq!(decode("abc").unwrap_err(), "hex data has an odd length (3)");
+        assert_eq!(decode("0g").unwrap_err(), "invalid hex data at character 1");
+        assert!(decode("+f").is_err());
+        assert!(decode("é0").is_err());
+    }
+}
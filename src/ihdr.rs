@@ -0,0 +1,189 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::ReasonError;
+use crate::Result;
+
+use std::convert::TryFrom;
+
+/// Prefix of the errors of malformed IHDR chunks
+const ERROR_PREFIX: &str = "Bad IHDR";
+
+/// Color type of the image as described in the IHDR chunk
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ColorType {
+    Grayscale,
+    Rgb,
+    Palette,
+    GrayscaleAlpha,
+    Rgba,
+}
+
+impl ColorType {
+    /// Returns the number of channels of each pixel
+    pub fn channels(&self) -> u8 {
+        match self {
+            ColorType::Grayscale => 1,
+            ColorType::Rgb => 3,
+            ColorType::Palette => 1,
+            ColorType::GrayscaleAlpha => 2,
+            ColorType::Rgba => 4,
+        }
+    }
+}
+
+impl TryFrom<u8> for ColorType {
+    type Error = crate::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(ColorType::Grayscale),
+            2 => Ok(ColorType::Rgb),
+            3 => Ok(ColorType::Palette),
+            4 => Ok(ColorType::GrayscaleAlpha),
+            6 => Ok(ColorType::Rgba),
+            _ => Err(ReasonError::boxed(ERROR_PREFIX, format!("Unknown color type {value}"))),
+        }
+    }
+}
+
+/// View over the data of an IHDR chunk
+#[derive(PartialEq, Eq, Debug)]
+pub struct Ihdr {
+    width: u32,
+    height: u32,
+    bit_depth: u8,
+    color_type: u8,
+    compression_method: u8,
+    filter_method: u8,
+    interlace_method: u8,
+}
+
+impl Ihdr {
+    const LENGTH: usize = 13;
+
+    /// Returns the width of the image in pixels
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the image in pixels
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the number of bits per sample or per palette index
+    pub fn bit_depth(&self) -> u8 {
+        self.bit_depth
+    }
+
+    /// Returns the color type, failing on values not defined by the PNG spec
+    pub fn color_type(&self) -> Result<ColorType> {
+        ColorType::try_from(self.color_type)
+    }
+
+    /// Returns the compression method byte
+    pub fn compression_method(&self) -> u8 {
+        self.compression_method
+    }
+
+    /// Returns the filter method byte
+    pub fn filter_method(&self) -> u8 {
+        self.filter_method
+    }
+
+    /// Returns the interlace method byte
+    pub fn interlace_method(&self) -> u8 {
+        self.interlace_method
+    }
+}
+
+impl TryFrom<&Chunk> for Ihdr {
+    type Error = crate::Error;
+
+    fn try_from(chunk: &Chunk) -> Result<Self> {
+        if chunk.chunk_type() != &ChunkType::IHDR {
+            return Err(ReasonError::boxed(ERROR_PREFIX, format!("Chunk type {} is not IHDR", chunk.chunk_type())));
+        }
+
+        let data = chunk.data();
+        if data.len() != Ihdr::LENGTH {
+            return Err(ReasonError::boxed(ERROR_PREFIX, format!(
+                "IHDR data has size {} (expected {})",
+                data.len(),
+                Ihdr::LENGTH
+            )));
+        }
+
+        Ok(Ihdr {
+            width: u32::from_be_bytes(data[0..4].try_into()?),
+            height: u32::from_be_bytes(data[4..8].try_into()?),
+            bit_depth: data[8],
+            color_type: data[9],
+            compression_method: data[10],
+            filter_method: data[11],
+            interlace_method: data[12],
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn ihdr_chunk(color_type: u8) -> Chunk {
+        #[rustfmt::skip]
+        let data = vec![
+            0, 0, 3, 32,    // width
+            0, 0, 2, 88,    // height
+            8,              // bit depth
+            color_type,     // color type
+            0, 0, 0,        // compression, filter, interlace
+        ];
+        Chunk::new(ChunkType::from_str("IHDR").unwrap(), data)
+    }
+
+    #[test]
+    fn test_ihdr_fields() {
+        let ihdr = Ihdr::try_from(&ihdr_chunk(6)).unwrap();
+        assert_eq!(ihdr.width(), 800);
+        assert_eq!(ihdr.height(), 600);
+        assert_eq!(ihdr.bit_depth(), 8);
+        assert_eq!(ihdr.color_type().unwrap(), ColorType::Rgba);
+        assert_eq!(ihdr.interlace_method(), 0);
+    }
+
+    #[test]
+    fn test_color_types() {
+        let expected = [
+            (0, ColorType::Grayscale, 1),
+            (2, ColorType::Rgb, 3),
+            (3, ColorType::Palette, 1),
+            (4, ColorType::GrayscaleAlpha, 2),
+            (6, ColorType::Rgba, 4),
+        ];
+        for (byte, color_type, channels) in expected {
+            let ihdr = Ihdr::try_from(&ihdr_chunk(byte)).unwrap();
+            assert_eq!(ihdr.color_type().unwrap(), color_type);
+            assert_eq!(color_type.channels(), channels);
+        }
+    }
+
+    #[test]
+    fn test_unknown_color_type() {
+        for byte in [1, 5, 7, 255] {
+            let ihdr = Ihdr::try_from(&ihdr_chunk(byte)).unwrap();
+            assert!(ihdr.color_type().is_err());
+        }
+    }
+
+    #[test]
+    fn test_invalid_ihdr() {
+        let chunk = Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 12]);
+        assert!(Ihdr::try_from(&chunk).is_err());
+
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), vec![0; 13]);
+        assert!(Ihdr::try_from(&chunk).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use crate::chunk::Chunk;
+use crate::chunk_type::ChunkType;
+use crate::error::ReasonError;
+use crate::hex;
+use crate::png::Png;
+use crate::Result;
+
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the errors of malformed JSON dumps
+const ERROR_PREFIX: &str = "Bad JSON dump";
+
+/// JSON view of a chunk, its data is stored as a hex string
+#[derive(Serialize, Deserialize, Debug)]
+struct ChunkJson {
+    #[serde(rename = "type")]
+    chunk_type: String,
+    data: String,
+}
+
+/// JSON view of a chunk's header, as printed by `print --format json`
+#[derive(Serialize)]
+struct ChunkSummaryJson {
+    #[serde(rename = "type")]
+    chunk_type: String,
+    length: u32,
+    crc: u32,
+    critical: bool,
+    public: bool,
+}
+
+/// JSON view of a decoded message, non UTF-8 messages are stored as base64
+#[derive(Serialize)]
+struct MessageJson<'a> {
+    #[serde(rename = "type")]
+    chunk_type: &'a str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'static str>,
+}
+
+/// Describes the chunks of the PNG as a JSON array of `{"type", "length", "crc", "critical", "public"}` objects
+pub fn summary(png: &Png) -> Result<String> {
+    let chunks: Vec<ChunkSummaryJson> = png
+        .chunks()
+        .iter()
+        .map(|chunk| ChunkSummaryJson {
+            chunk_type: chunk.chunk_type().as_str().to_string(),
+            length: chunk.length(),
+            crc: chunk.crc(),
+            critical: chunk.chunk_type().is_critical(),
+            public: chunk.chunk_type().is_public(),
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&chunks)?)
+}
+
+/// Describes a message as a single line `{"type", "message"}` JSON object.
+/// If the message isn't valid UTF-8, it is base64 encoded and the object gets an `"encoding": "base64"` field
+pub fn message(chunk_type: &ChunkType, data: &[u8]) -> Result<String> {
+    let (message, encoding) = match std::str::from_utf8(data) {
+        Ok(message) => (message.to_string(), None),
+        Err(_) => (BASE64.encode(data), Some("base64")),
+    };
+    Ok(serde_json::to_string(&MessageJson { chunk_type: chunk_type.as_str(), message, encoding })?)
+}
+
+/// Dumps the chunks of the PNG as a JSON array of `{"type", "data"}` objects.
+/// The chunk types are written with their exact case, since it holds their property bits
+pub fn dump(png: &Png) -> Result<String> {
+    let chunks: Vec<ChunkJson> = png
+        .chunks()
+        .iter()
+        .map(|chunk| ChunkJson {
+            chunk_type: chunk.chunk_type().as_str().to_string(),
+            data: hex::encode(chunk.data()),
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&chunks)?)
+}
+
+/// Loads a PNG from a JSON dump, the CRC of every chunk is recomputed
+pub fn load(json: &str) -> Result<Png> {
+    let chunks: Vec<ChunkJson> = serde_json::from_str(json)?;
+    let chunks = chunks
+        .into_iter()
+        .enumerate()
+        .map(|(index, chunk)| {
+            let chunk_type = ChunkType::from_str(&chunk.chunk_type)
+                .map_err(|reason| ReasonError::boxed(ERROR_PREFIX, format!("chunk {index}: {reason}")))?;
+            let data = hex::decode(&chunk.data).map_err(|reason| ReasonError::boxed(ERROR_PREFIX, format!("chunk {index}: {reason}")))?;
+            Chunk::try_new(chunk_type, data)
+        })
+        .collect::<Result<Vec<Chunk>>>()?;
+    Ok(Png::from_chunks(chunks))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    #[test]
+    fn test_round_trip_preserves_case() {
+        let png = Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("ruSt", b"lowercase first letter"),
+            chunk("RUST", &[0, 255, 16]),
+            chunk("IEND", &[]),
+        ]);
+
+        let loaded = load(&dump(&png).unwrap()).unwrap();
+        assert_eq!(loaded.as_bytes(), png.as_bytes());
+        assert_eq!(loaded.chunks()[1].chunk_type().as_str(), "ruSt");
+        assert!(!loaded.chunks()[1].chunk_type().is_critical());
+        assert!(loaded.chunks()[2].chunk_type().is_critical());
+    }
+
+    #[test]
+    fn test_dump_keeps_type_case() {
+        let json = dump(&Png::from_chunks(vec![chunk("ruSt", b"hi")])).unwrap();
+        assert!(json.contains("\"ruSt\""));
+        assert!(!json.contains("\"RUST\""));
+    }
+
+    #[test]
+    fn test_load_invalid_dump() {
+        assert!(load(r#"[{"type": "ru5t", "data": ""}]"#).is_err());
+        assert!(load(r#"[{"type": "ruSt", "data": "abc"}]"#).is_err());
+        assert!(load(r#"[{"type": "ruSt", "data": "zz"}]"#).is_err());
+        assert!(load(r#"{"type": "ruSt"}"#).is_err());
+    }
+
+    #[test]
+    fn test_summary() {
+        let png = Png::from_chunks(vec![chunk("IHDR", &[0; 13]), chunk("ruSt", b"hi")]);
+        let summary: serde_json::Value = serde_json::from_str(&summary(&png).unwrap()).unwrap();
+
+        assert_eq!(summary[0]["type"], "IHDR");
+        assert_eq!(summary[0]["length"], 13);
+        assert_eq!(summary[0]["critical"], true);
+        assert_eq!(summary[1]["type"], "ruSt");
+        assert_eq!(summary[1]["crc"], png.chunks()[1].crc());
+        assert_eq!(summary[1]["critical"], false);
+        assert_eq!(summary[1]["public"], false);
+    }
+
+    #[test]
+    fn test_message() {
+        let chunk_type = ChunkType::from_str("ruSt").unwrap();
+        let text: serde_json::Value = serde_json::from_str(&message(&chunk_type, b"hello").unwrap()).unwrap();
+        assert_eq!(text["type"], "ruSt");
+        assert_eq!(text["message"], "hello");
+        assert!(text.get("encoding").is_none());
+
+        let binary: serde_json::Value = serde_json::from_str(&message(&chunk_type, &[0xff, 0x00, 0x80]).unwrap()).unwrap();
+        assert_eq!(binary["message"], "/wCA");
+        assert_eq!(binary["encoding"], "base64");
+    }
+}
@@ -0,0 +1,24 @@
+pub mod archive;
+pub mod background;
+pub mod chain;
+pub mod chunk;
+pub mod chunk_type;
+pub mod codec;
+pub mod crypto;
+pub mod error;
+pub mod exif;
+pub mod hex;
+pub mod ihdr;
+pub mod json;
+pub mod lint;
+pub mod png;
+pub mod sniff;
+pub mod splice;
+pub mod text;
+
+pub use crate::chunk::{Chunk, ChunkDecodingError};
+pub use crate::chunk_type::ChunkType;
+pub use crate::png::Png;
+
+pub type Error = Box<dyn std::error::Error>;
+pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,120 @@
+use crate::png::Png;
+
+use std::collections::HashMap;
+
+/// Standard critical chunk types
+const CRITICAL_CHUNKS: [&[u8; 4]; 4] = [b"IHDR", b"PLTE", b"IDAT", b"IEND"];
+
+/// Chunk types that may appear at most once in a file
+const SINGLETON_CHUNKS: [&[u8; 4]; 14] = [
+    b"IHDR", b"PLTE", b"IEND", b"cHRM", b"gAMA", b"iCCP", b"sBIT",
+    b"sRGB", b"bKGD", b"hIST", b"tRNS", b"pHYs", b"tIME", b"eXIf",
+];
+
+/// Chunk types that must appear before PLTE and IDAT
+const BEFORE_PLTE_CHUNKS: [&[u8; 4]; 5] = [b"cHRM", b"gAMA", b"iCCP", b"sBIT", b"sRGB"];
+
+/// Chunk types that must appear after PLTE (when present) and before IDAT
+const AFTER_PLTE_CHUNKS: [&[u8; 4]; 3] = [b"bKGD", b"hIST", b"tRNS"];
+
+/// Chunk types that must appear before IDAT
+const BEFORE_IDAT_CHUNKS: [&[u8; 4]; 3] = [b"pHYs", b"sPLT", b"eXIf"];
+
+/// Returns the warnings about structural conventions of the PNG spec that the file doesn't follow
+/// (duplicated singleton chunks, misplaced chunks and unknown critical chunks)
+pub fn lint(png: &Png) -> Vec<String> {
+    let types: Vec<[u8; 4]> = png.chunks().iter().map(|chunk| chunk.chunk_type().bytes()).collect();
+    let first = |chunk_type: &[u8; 4]| types.iter().position(|elem| elem == chunk_type);
+    let plte = first(b"PLTE");
+    let idat = first(b"IDAT");
+    let iend = first(b"IEND");
+    let before_idat = [&BEFORE_PLTE_CHUNKS[..], &AFTER_PLTE_CHUNKS, &BEFORE_IDAT_CHUNKS, &[b"PLTE"]].concat();
+
+    let mut warnings = vec![];
+    let mut counts: HashMap<[u8; 4], usize> = HashMap::new();
+    for (index, chunk) in png.chunks().iter().enumerate() {
+        let chunk_type = chunk.chunk_type();
+        let bytes = chunk_type.bytes();
+        let mut warn = |reason: &str| warnings.push(format!("chunk {index} ({chunk_type}): {reason}"));
+
+        let count = counts.entry(bytes).or_insert(0);
+        *count += 1;
+        if *count == 2 && SINGLETON_CHUNKS.contains(&&bytes) {
+            warn("duplicate of a chunk that must appear at most once");
+        }
+
+        if chunk_type.is_critical() && !CRITICAL_CHUNKS.contains(&&bytes) {
+            warn("unknown critical chunk");
+        }
+
+        if &bytes == b"IHDR" && index != 0 {
+            warn("IHDR should be the first chunk");
+        }
+        if iend.is_some_and(|iend| index > iend) {
+            warn("placed after IEND");
+        }
+        if BEFORE_PLTE_CHUNKS.contains(&&bytes) && plte.is_some_and(|plte| index > plte) {
+            warn("should be placed before PLTE");
+        }
+        if AFTER_PLTE_CHUNKS.contains(&&bytes) && plte.is_some_and(|plte| index < plte) {
+            warn("should be placed after PLTE");
+        }
+        if before_idat.contains(&&bytes) && idat.is_some_and(|idat| index > idat) {
+            warn("should be placed before the first IDAT");
+        }
+    }
+
+    if iend.is_none() {
+        warnings.push("missing IEND chunk".to_string());
+    }
+
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use std::str::FromStr;
+
+    fn png_from_types(types: &[&str]) -> Png {
+        Png::from_chunks(
+            types
+                .iter()
+                .map(|chunk_type| Chunk::new(ChunkType::from_str(chunk_type).unwrap(), vec![]))
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_lint_valid_png() {
+        let png = png_from_types(&["IHDR", "gAMA", "PLTE", "tRNS", "IDAT", "IDAT", "tEXt", "IEND"]);
+        assert!(lint(&png).is_empty());
+    }
+
+    #[test]
+    fn test_lint_doubled_ihdr() {
+        let png = png_from_types(&["IHDR", "IHDR", "IDAT", "IEND"]);
+        let warnings = lint(&png);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("duplicate"));
+        assert!(warnings[1].contains("first chunk"));
+    }
+
+    #[test]
+    fn test_lint_misplaced_ancillary() {
+        let png = png_from_types(&["IHDR", "IDAT", "gAMA", "IEND", "ruSt"]);
+        let warnings = lint(&png);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("before the first IDAT"));
+        assert!(warnings[1].contains("after IEND"));
+    }
+
+    #[test]
+    fn test_lint_unknown_critical() {
+        let png = png_from_types(&["IHDR", "RUST", "IDAT", "IEND"]);
+        let warnings = lint(&png);
+        assert_eq!(warnings, vec!["chunk 1 (RUST): unknown critical chunk"]);
+    }
+}
@@ -1,37 +1,134 @@
+use crate::background::Background;
 use crate::chunk::Chunk;
 use crate::chunk_type::ChunkType;
+use crate::ihdr::Ihdr;
+use crate::splice::read_header;
+use crate::text;
 
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::convert::TryFrom;
 use std::fmt::Display;
-use std::io::{BufReader, Read};
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+
+use memmap2::Mmap;
 
 pub struct Png {
     chunks: Vec<Chunk>,
 }
 
+/// Position of a chunk relatively to the PLTE and IDAT chunks, as used by the ordering rules of the PNG spec
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum PlacementGroup {
+    BeforePlte,
+    Plte,
+    BeforeIdat,
+    Idat,
+    AfterIdat,
+}
+
+/// Difference between two PNGs, as found by `Png::diff`. Indices are positions in the PNG holding the chunk
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+pub enum ChunkDiff {
+    /// The chunk is only in the other PNG
+    Added { index: usize, chunk_type: ChunkType },
+    /// The chunk is only in this PNG
+    Removed { index: usize, chunk_type: ChunkType },
+    /// The chunks hold different data
+    Changed { index: usize, other_index: usize, chunk_type: ChunkType },
+}
+
+impl Display for PlacementGroup {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlacementGroup::BeforePlte => write!(f, "before PLTE"),
+            PlacementGroup::Plte => write!(f, "PLTE"),
+            PlacementGroup::BeforeIdat => write!(f, "before IDAT"),
+            PlacementGroup::Idat => write!(f, "IDAT"),
+            PlacementGroup::AfterIdat => write!(f, "after IDAT"),
+        }
+    }
+}
+
 impl Png {
-    const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+    pub const STANDARD_HEADER: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+
+    /// Maximum number of chunks parsed by default (one million), far more than real images hold.
+    /// Files declaring more chunks are rejected instead of growing the chunk list without bound
+    pub const DEFAULT_MAX_CHUNKS: usize = 1_000_000;
 
     pub fn from_chunks(chunks: Vec<Chunk>) -> Png {
         Png { chunks }
     }
 
+    /// Consumes the PNG and returns its chunks, the counterpart of `from_chunks`
+    pub fn into_chunks(self) -> Vec<Chunk> {
+        self.chunks
+    }
+
+    /// Appends the chunk, before the IEND chunk if it is the last one so that IEND stays last
     pub fn append_chunk(&mut self, chunk: Chunk) {
-        self.chunks.push(chunk)
+        match self.chunks.last() {
+            Some(last) if last.chunk_type() == &ChunkType::IEND => self.chunks.insert(self.chunks.len() - 1, chunk),
+            _ => self.chunks.push(chunk),
+        }
     } 
 
+    /// Inserts the chunk at the given position, shifting the following chunks.
+    /// Inserting at `chunks().len()` is the same as appending
+    pub fn insert_chunk(&mut self, index: usize, chunk: Chunk) -> crate::Result<()> {
+        if index > self.chunks.len() {
+            return Err(PngError::boxed(format!("Can't insert chunk at index {index}, the PNG only has {} chunks", self.chunks.len())));
+        }
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    /// Removes the first chunk of the given type.
+    /// IHDR and IEND are refused, as other viewers reject a PNG without them (see `force_remove_chunk`)
     pub fn remove_chunk(&mut self, chunk_type: ChunkType) -> crate::Result<Chunk> {
+        if Self::is_protected(&chunk_type) {
+            return Err(PngError::boxed(format!("refusing to remove critical chunk {chunk_type}")));
+        }
+        self.force_remove_chunk(chunk_type)
+    }
+
+    /// Removes and returns the chunk at the given position, whatever its type
+    pub fn remove_chunk_by_index(&mut self, index: usize) -> crate::Result<Chunk> {
+        if index >= self.chunks.len() {
+            return Err(PngError::boxed(format!("No chunk at index {index}, the PNG only has {} chunks", self.chunks.len())));
+        }
+        Ok(self.chunks.remove(index))
+    }
+
+    /// Returns true for the chunk types that `remove_chunk` refuses to remove
+    pub fn is_protected(chunk_type: &ChunkType) -> bool {
+        matches!(*chunk_type, ChunkType::IHDR | ChunkType::IEND)
+    }
+
+    /// Removes the first chunk of the given type, even if it is IHDR or IEND
+    pub fn force_remove_chunk(&mut self, chunk_type: ChunkType) -> crate::Result<Chunk> {
         if let Some(chunk_index) = self.chunks
                                 .iter()
                                 .position(|elem| elem.chunk_type() == &chunk_type)
         {
             Ok(self.chunks.remove(chunk_index))
-        } else 
-        {
+        } else {
             Err( PngError::boxed(format!("Can't find chunk with type {chunk_type}")) )
         }
     }
 
+    /// Changes the type of the first chunk of the `old` type, keeping its data (the CRC follows the new type)
+    pub fn rename_chunk(&mut self, old: ChunkType, new: ChunkType) -> crate::Result<()> {
+        let chunk = self.chunks
+            .iter_mut()
+            .find(|chunk| chunk.chunk_type() == &old)
+            .ok_or_else(|| PngError::boxed(format!("Can't find chunk with type {old}")))?;
+        *chunk = Chunk::new(new, chunk.data().to_vec());
+        Ok(())
+    }
+
     pub fn header(&self) -> &[u8; 8] {
         &Self::STANDARD_HEADER
     }
@@ -40,57 +137,614 @@ impl Png {
         &self.chunks
     }
 
+    /// Returns the number of chunks
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Returns true if the PNG has no chunks
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Returns the index and chunk of every chunk whose data contains the needle
+    pub fn find(&self, needle: &[u8]) -> Vec<(usize, &Chunk)> {
+        self.find_matching(needle, false)
+    }
+
+    /// Same as `find`, ignoring the case of the needle (see `Chunk::match_offsets`)
+    pub fn find_ignore_case(&self, needle: &[u8]) -> Vec<(usize, &Chunk)> {
+        self.find_matching(needle, true)
+    }
+
+    fn find_matching(&self, needle: &[u8], ignore_case: bool) -> Vec<(usize, &Chunk)> {
+        self.chunks
+            .iter()
+            .enumerate()
+            .filter(|(_, chunk)| !chunk.match_offsets(needle, ignore_case).is_empty())
+            .collect()
+    }
+
+    /// Returns the chunk at the given position, as numbered by the `list` command
+    pub fn chunk_by_index(&self, index: usize) -> Option<&Chunk> {
+        self.chunks.get(index)
+    }
+
+    /// Returns a mutable reference to the chunk at the given position
+    pub fn chunk_at_mut(&mut self, index: usize) -> Option<&mut Chunk> {
+        self.chunks.get_mut(index)
+    }
+
+    /// Sets the data of every chunk with the given type, returning the number of updated chunks
+    pub fn replace_all(&mut self, chunk_type: ChunkType, data: Vec<u8>) -> usize {
+        let mut replaced = 0;
+        for chunk in self.chunks.iter_mut().filter(|chunk| chunk.chunk_type() == &chunk_type) {
+            chunk.set_data(data.clone());
+            replaced += 1;
+        }
+        replaced
+    }
+
+    /// Returns every chunk of the given type, in the order they appear in the file
+    pub fn chunks_by_type(&self, chunk_type: ChunkType) -> Vec<&Chunk> {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == &chunk_type)
+            .collect()
+    }
+
     pub fn chunk_by_type(&self, chunk_type: ChunkType) -> Option<&Chunk> {
         if let Some(chunk_index) = self.chunks
                                 .iter()
                                 .position(|elem| elem.chunk_type() == &chunk_type)
         {
             Some(&self.chunks[chunk_index])
-        } else 
-        {
+        } else {
             None 
         }
     }
 
-    pub fn as_bytes(&self) -> Vec<u8> {
-        let mut result = self.header().to_vec();
-        for chunk in &self.chunks {
-            result.extend(chunk.as_bytes());
+    /// Parses the bytes like `try_from` without validating the 8-byte signature,
+    /// e.g. to recover a file whose signature got corrupted
+    pub fn try_from_forced_signature(bytes: &[u8]) -> crate::Result<Png> {
+        Png::try_from_forced_signature_with_limit(bytes, Png::DEFAULT_MAX_CHUNKS)
+    }
+
+    /// Parses the bytes like `try_from_forced_signature`, failing once more than `max_chunks` chunks are found
+    pub fn try_from_forced_signature_with_limit(bytes: &[u8], max_chunks: usize) -> crate::Result<Png> {
+        let header_length = Png::STANDARD_HEADER.len();
+        if bytes.len() < header_length {
+            return Err(PngError::boxed(format!("File is too short ({} bytes) to hold a signature", bytes.len())));
         }
-        result
+
+        let fixed: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .chain(&bytes[header_length..])
+            .copied()
+            .collect();
+        Png::try_from_with_limit(fixed.as_slice(), max_chunks)
     }
-}
 
-impl TryFrom<&[u8]> for Png {
-    type Error = crate::Error;
-    fn try_from(bytes: &[u8]) -> crate::Result<Png> {
+    /// Parses the chunks following the PNG header.
+    /// Parsing stops when the remaining bytes can't hold a whole chunk, 
+    /// the unconsumed bytes are returned alongside the chunks.
+    pub fn parse_chunks(bytes: &[u8]) -> crate::Result<(Vec<Chunk>, &[u8])> {
+        Png::parse_chunks_with_progress(bytes, |_| {})
+    }
+
+    /// Parses the chunks like `parse_chunks`, calling `on_chunk` with the number of bytes consumed so far
+    /// after each parsed chunk, e.g. to report the progress of parsing a large file
+    pub fn parse_chunks_with_progress(bytes: &[u8], on_chunk: impl FnMut(usize)) -> crate::Result<(Vec<Chunk>, &[u8])> {
+        let (chunks, end) = Png::parse_chunks_from(bytes, 0, Png::DEFAULT_MAX_CHUNKS, on_chunk)?;
+        Ok((chunks, &bytes[end..]))
+    }
+
+    /// Parses the chunks starting at `start` in the buffer, returning them with the offset where parsing stopped.
+    /// Offsets passed to `on_chunk` and reported in errors are positions in the whole buffer.
+    /// Fails once more than `max_chunks` chunks are found
+    fn parse_chunks_from(
+        bytes: &[u8],
+        start: usize,
+        max_chunks: usize,
+        mut on_chunk: impl FnMut(usize),
+    ) -> crate::Result<(Vec<Chunk>, usize)> {
+        let mut chunks = vec![];
+        let mut offset = start;
+        while bytes.len() - offset >= 4 {
+            let length = u32::from_be_bytes(bytes[offset..offset + 4].try_into()?) as usize;
+            // Size of length + size of type + size of data + size of CRC.
+            // Lengths over the maximum are left to `from_bytes_at`, which fails on them
+            if length <= Chunk::MAX_LENGTH && 4 + 4 + length + 4 > bytes.len() - offset {
+                break;
+            }
+
+            check_chunk_count(chunks.len(), max_chunks)?;
+            let (chunk, consumed) = Chunk::from_bytes_at(bytes, offset)?;
+            chunks.push(chunk);
+            offset += consumed;
+            on_chunk(offset);
+        }
+        Ok((chunks, offset))
+    }
+
+    /// Buckets the chunk types in the placement groups of the PNG spec, every group being listed in file order.
+    /// Without a PLTE chunk, the chunks preceding the first IDAT are all in the before IDAT group.
+    /// Chunks other than IDAT following the first IDAT are in the after IDAT group.
+    pub fn placement_report(&self) -> Vec<(PlacementGroup, Vec<ChunkType>)> {
+        let first = |chunk_type: &[u8; 4]| self.chunks.iter().position(|chunk| &chunk.chunk_type().bytes() == chunk_type);
+        let plte = first(b"PLTE");
+        let idat = first(b"IDAT");
+
+        let mut report: Vec<(PlacementGroup, Vec<ChunkType>)> = [
+            PlacementGroup::BeforePlte,
+            PlacementGroup::Plte,
+            PlacementGroup::BeforeIdat,
+            PlacementGroup::Idat,
+            PlacementGroup::AfterIdat,
+        ]
+        .into_iter()
+        .map(|group| (group, vec![]))
+        .collect();
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let group = match &chunk.chunk_type().bytes() {
+                b"PLTE" => PlacementGroup::Plte,
+                b"IDAT" => PlacementGroup::Idat,
+                _ if idat.is_some_and(|idat| index > idat) => PlacementGroup::AfterIdat,
+                _ if plte.is_some_and(|plte| index < plte) => PlacementGroup::BeforePlte,
+                _ => PlacementGroup::BeforeIdat,
+            };
+            if let Some((_, types)) = report.iter_mut().find(|(elem, _)| *elem == group) {
+                types.push(*chunk.chunk_type());
+            }
+        }
+        report
+    }
+
+    /// Parses the PNG file at the given path by memory-mapping it instead of reading it in a `Vec`.
+    /// Only the chunk data gets copied on the heap, so parsing needs about the file size in memory
+    /// instead of twice the file size with `fs::read` followed by `try_from`
+    pub fn try_from_path(path: &Path) -> crate::Result<Png> {
+        Png::try_from_path_with_progress(path, Png::DEFAULT_MAX_CHUNKS, |_| {})
+    }
+
+    /// Parses the PNG file like `try_from_path` with at most `max_chunks` chunks, calling `on_chunk`
+    /// with the number of bytes of the file parsed so far (signature included) after each chunk
+    pub fn try_from_path_with_progress(path: &Path, max_chunks: usize, on_chunk: impl FnMut(usize)) -> crate::Result<Png> {
+        let file = File::open(path)?;
+        // SAFETY: the map is only read while parsing, the chunks own copies of their data.
+        // As with any memory map, the file must not be truncated by another process meanwhile
+        let map = unsafe { Mmap::map(&file)? };
+        Png::parse_with_progress(&map[..], max_chunks, on_chunk)
+    }
+
+    /// Parses a PNG from a stream (e.g. stdin or a socket), reading one chunk at a time
+    /// and checking its CRC as soon as it is read. The stream must end right after the last chunk
+    pub fn from_reader<R: Read>(reader: R) -> crate::Result<Png> {
+        Png::from_reader_with_limit(reader, Png::DEFAULT_MAX_CHUNKS)
+    }
+
+    /// Parses a PNG from a stream like `from_reader`, failing once more than `max_chunks` chunks are found
+    pub fn from_reader_with_limit<R: Read>(mut reader: R, max_chunks: usize) -> crate::Result<Png> {
+        let mut header = [0u8; 8];
+        let read = read_header(&mut reader, &mut header)?;
+        if read < header.len() {
+            return Err(PngError::boxed(format!("invalid PNG signature (only {read} bytes)")));
+        }
+        if header != Png::STANDARD_HEADER {
+            return Err(PngError::boxed(format!("invalid PNG signature (expected {:?}, found {:?})", Png::STANDARD_HEADER, header)));
+        }
+
+        let mut chunks = vec![];
+        loop {
+            // Length + type of the next chunk
+            let mut chunk_header = [0u8; 8];
+            match read_header(&mut reader, &mut chunk_header)? {
+                0 => break,
+                8 => {},
+                read => return Err(PngError::boxed(format!("Truncated chunk header ({read} bytes)"))),
+            }
+            check_chunk_count(chunks.len(), max_chunks)?;
+            let length = u32::from_be_bytes(chunk_header[0..4].try_into()?) as usize;
+            Chunk::check_length(length)?;
+
+            // Data + CRC, read into a buffer that only grows as the bytes arrive
+            let mut bytes = chunk_header.to_vec();
+            (&mut reader).take(length as u64 + 4).read_to_end(&mut bytes)?;
+            if bytes.len() < 12 + length {
+                return Err(PngError::boxed(format!("Truncated chunk (missing {} bytes)", 12 + length - bytes.len())));
+            }
+            chunks.push(Chunk::try_from(bytes.as_slice())?);
+        }
+        Ok(Png { chunks })
+    }
+
+    /// Parses the bytes like `try_from`, failing once more than `max_chunks` chunks are found
+    pub fn try_from_with_limit(bytes: &[u8], max_chunks: usize) -> crate::Result<Png> {
+        Png::parse_with_progress(bytes, max_chunks, |_| {})
+    }
+
+    /// Checks the signature then parses the chunks, which must use every remaining byte
+    fn parse_with_progress(bytes: &[u8], max_chunks: usize, on_chunk: impl FnMut(usize)) -> crate::Result<Png> {
         let mut reader = BufReader::new(bytes);
         let mut header = [0u8; 8];
-        reader.read_exact(&mut header)?;
+        if reader.read_exact(&mut header).is_err() {
+            return Err(PngError::boxed(
+                format!("invalid PNG signature (only {} bytes)", bytes.len())
+            ))
+        }
         if header != Png::STANDARD_HEADER {
             return Err(PngError::boxed(
-                "Invalid PNG header found".to_string()
+                format!("invalid PNG signature (expected {:?}, found {:?})", Png::STANDARD_HEADER, header)
+            ))
+        }
+
+        let (chunks, end) = Png::parse_chunks_from(bytes, Png::STANDARD_HEADER.len(), max_chunks, on_chunk)?;
+        if end < bytes.len() {
+            return Err(PngError::boxed(
+                format!("{} trailing bytes after the last chunk at offset {}", bytes.len() - end, end)
             ))
         }
 
+        Ok ( Png { chunks } )
+    }
+
+    /// Parses the IHDR chunk, which the PNG spec requires to be the first chunk
+    pub fn ihdr(&self) -> crate::Result<Ihdr> {
+        let first = self.chunks.first().ok_or_else(|| PngError::boxed("Can't read IHDR from a PNG without chunks".to_string()))?;
+        Ihdr::try_from(first)
+    }
+
+    /// Returns the width and height of the image, or `None` if the first chunk isn't a valid IHDR chunk
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        self.ihdr().ok().map(|ihdr| (ihdr.width(), ihdr.height()))
+    }
+
+    /// Returns the background color of the bKGD chunk, interpreted with the color type of the IHDR chunk
+    pub fn background(&self) -> crate::Result<Option<Background>> {
+        let Some(bkgd) = self.chunks.iter().find(|chunk| &chunk.chunk_type().bytes() == b"bKGD") else {
+            return Ok(None);
+        };
+        let ihdr = self.chunks
+            .iter()
+            .find(|chunk| chunk.chunk_type() == &ChunkType::IHDR)
+            .ok_or_else(|| PngError::boxed("Can't read bKGD without an IHDR chunk".to_string()))?;
+
+        let color_type = Ihdr::try_from(ihdr)?.color_type()?;
+        Ok(Some(Background::parse(color_type, bkgd.data())?))
+    }
+
+    /// Returns the first private, ancillary and safe to copy chunk type (`ruSt`, then `ruAa`, `ruAb`...)
+    /// that no chunk of the file uses
+    pub fn unused_private_type(&self) -> Option<ChunkType> {
+        let candidates = (b'A'..=b'Z').flat_map(|third| (b'a'..=b'z').map(move |fourth| [b'r', b'u', third, fourth]));
+        std::iter::once(*b"ruSt")
+            .chain(candidates)
+            .filter_map(|code| ChunkType::try_from(code).ok())
+            .find(|chunk_type| self.chunk_by_type(*chunk_type).is_none())
+    }
+
+    /// Counts the chunks of each type, sorted by chunk type
+    pub fn count_by_type(&self) -> BTreeMap<ChunkType, usize> {
+        let mut counts = BTreeMap::new();
+        for chunk in &self.chunks {
+            *counts.entry(*chunk.chunk_type()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Compares the chunks with another PNG. The n-th chunk of a type is matched with the n-th
+    /// chunk of the same type in the other PNG, so moving a chunk isn't reported as a change.
+    /// Removed and changed chunks come first in the order of this PNG, then added chunks in the order of the other one
+    pub fn diff(&self, other: &Png) -> Vec<ChunkDiff> {
+        let other_index = other.build_type_index();
+        let mut seen: HashMap<[u8; 4], usize> = HashMap::new();
+        let mut diffs = vec![];
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            let chunk_type = *chunk.chunk_type();
+            let occurrence = seen.entry(chunk_type.bytes()).or_default();
+            match other_index.get(&chunk_type.bytes()).and_then(|positions| positions.get(*occurrence)) {
+                Some(&other_position) if other.chunks[other_position].data() != chunk.data() => {
+                    diffs.push(ChunkDiff::Changed { index, other_index: other_position, chunk_type });
+                },
+                Some(_) => {},
+                None => diffs.push(ChunkDiff::Removed { index, chunk_type }),
+            }
+            *occurrence += 1;
+        }
+
+        let mut added: Vec<usize> = other_index
+            .iter()
+            .flat_map(|(chunk_type, positions)| positions.iter().skip(seen.get(chunk_type).copied().unwrap_or(0)))
+            .copied()
+            .collect();
+        added.sort_unstable();
+        diffs.extend(added.into_iter().map(|index| ChunkDiff::Added { index, chunk_type: *other.chunks[index].chunk_type() }));
+        diffs
+    }
+
+    /// Maps every chunk type to the indices of its chunks, in order. Build it once to look up many types
+    /// without scanning every chunk each time (`chunk_by_type` stays the simplest for one lookup)
+    pub fn build_type_index(&self) -> HashMap<[u8; 4], Vec<usize>> {
+        let mut index: HashMap<[u8; 4], Vec<usize>> = HashMap::new();
+        for (position, chunk) in self.chunks.iter().enumerate() {
+            index.entry(chunk.chunk_type().bytes()).or_default().push(position);
+        }
+        index
+    }
+
+    /// Rewrites the type of every standard chunk to its canonical case (and thus its CRC),
+    /// returning the index and previous type of every corrected chunk. Custom chunks are left untouched
+    pub fn fix_case(&mut self) -> Vec<(usize, ChunkType)> {
+        let mut corrected = vec![];
+        for (index, chunk) in self.chunks.iter_mut().enumerate() {
+            match chunk.chunk_type().canonical() {
+                Some(canonical) if &canonical != chunk.chunk_type() => {
+                    corrected.push((index, *chunk.chunk_type()));
+                    *chunk = Chunk::new(canonical, chunk.data().to_vec());
+                }
+                _ => {}
+            }
+        }
+        corrected
+    }
+
+    /// Returns the EXIF data of the eXIf chunk, if any
+    pub fn exif(&self) -> Option<&[u8]> {
+        self.chunks
+            .iter()
+            .find(|chunk| &chunk.chunk_type().bytes() == b"eXIf")
+            .map(|chunk| chunk.data())
+    }
+
+    /// Stores the EXIF data in the eXIf chunk, replacing the existing one or adding a new chunk
+    /// before the first IDAT (as the PNG spec requires) or before IEND
+    pub fn set_exif(&mut self, data: Vec<u8>) -> crate::Result<()> {
+        if let Some(chunk) = self.chunks.iter_mut().find(|chunk| &chunk.chunk_type().bytes() == b"eXIf") {
+            chunk.set_data(data);
+            return Ok(());
+        }
+
+        let chunk = Chunk::try_new(ChunkType::from_bytes_unchecked(*b"eXIf"), data)?;
+        let index = self.chunks
+            .iter()
+            .position(|chunk| matches!(*chunk.chunk_type(), ChunkType::IDAT | ChunkType::IEND))
+            .unwrap_or(self.chunks.len());
+        self.chunks.insert(index, chunk);
+        Ok(())
+    }
+
+    /// Returns the number of IDAT chunks and the total size of their data.
+    /// Many small IDAT chunks waste 12 bytes of overhead each
+    pub fn idat_stats(&self) -> (usize, usize) {
+        self.chunks
+            .iter()
+            .filter(|chunk| chunk.chunk_type() == &ChunkType::IDAT)
+            .fold((0, 0), |(count, total_bytes), chunk| (count + 1, total_bytes + chunk.data().len()))
+    }
+
+    /// Merges every run of consecutive IDAT chunks into a single IDAT chunk, as long as the merged data
+    /// fits in a chunk. The image data bytes are unchanged, only the chunk boundaries are removed.
+    /// Returns the number of removed chunks
+    pub fn merge_idat(&mut self) -> usize {
+        let before = self.chunks.len();
+        let mut merged: Vec<Chunk> = Vec::with_capacity(before);
+        let mut run: Vec<Chunk> = vec![];
+        let mut run_length = 0;
+        for chunk in std::mem::take(&mut self.chunks) {
+            if chunk.chunk_type() == &ChunkType::IDAT && run_length + chunk.data().len() <= Chunk::MAX_LENGTH {
+                run_length += chunk.data().len();
+                run.push(chunk);
+                continue;
+            }
+            merged.extend(merge_run(std::mem::take(&mut run), run_length));
+            run_length = 0;
+            if chunk.chunk_type() == &ChunkType::IDAT {
+                run_length = chunk.data().len();
+                run.push(chunk);
+            } else {
+                merged.push(chunk);
+            }
+        }
+        merged.extend(merge_run(run, run_length));
+        self.chunks = merged;
+        before - self.chunks.len()
+    }
+
+    /// Removes the ancillary chunks equal (same type and data) to an earlier chunk, keeping the first occurrences in order.
+    /// Critical chunks are never removed, as repeated IDAT data is part of the image. Returns the number of removed chunks
+    pub fn dedup_chunks(&mut self) -> usize {
+        let before = self.chunks.len();
+        let mut seen = HashSet::new();
+        let keep: Vec<bool> = self.chunks
+            .iter()
+            .map(|chunk| chunk.chunk_type().is_critical() || seen.insert(chunk))
+            .collect();
+        let mut keep = keep.into_iter();
+        self.chunks.retain(|_| keep.next().expect("one flag per chunk"));
+        before - self.chunks.len()
+    }
+
+    /// Returns the keyword and text of every tEXt and zTXt chunk in file order, skipping the malformed ones
+    pub fn text_chunks(&self) -> Vec<(String, String)> {
+        self.chunks
+            .iter()
+            .filter_map(|chunk| match &chunk.chunk_type().bytes() {
+                b"tEXt" => chunk.as_text().ok(),
+                b"zTXt" => text::decode_ztxt(chunk.data()).ok(),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Removes every ancillary chunk, returning the removed chunks in their original order
+    pub fn retain_critical(&mut self) -> Vec<Chunk> {
+        let (critical, ancillary) = std::mem::take(&mut self.chunks)
+            .into_iter()
+            .partition(|chunk| chunk.chunk_type().is_critical());
+        self.chunks = critical;
+        ancillary
+    }
+
+    /// Parses the bytes without stopping at the first problem.
+    /// Chunks with a CRC mismatch are kept (their CRC is recomputed when serialized) while chunks
+    /// that can't be decoded are skipped, every problem being reported with its offset in the file.
+    pub fn try_from_lenient(bytes: &[u8]) -> (Png, Vec<ChunkError>) {
+        Png::try_from_lenient_with_limit(bytes, Png::DEFAULT_MAX_CHUNKS)
+    }
+
+    /// Parses the bytes like `try_from_lenient`, reporting a problem and stopping once more than `max_chunks` chunks are found
+    pub fn try_from_lenient_with_limit(bytes: &[u8], max_chunks: usize) -> (Png, Vec<ChunkError>) {
         let mut chunks = vec![];
-        let mut length_buffer = [0u8; 4];
-        while let Ok(()) = reader.read_exact(&mut length_buffer) {
-            // Size of type + size of data + size of CRC
-            let final_pos = 4 + u32::from_be_bytes(length_buffer) + 4;
-            let mut chunk_buffer = vec![0; final_pos as usize];
-            reader.read_exact(&mut chunk_buffer)?;
-            let all_bytes: Vec<_> = length_buffer
-                .iter()
-                .copied()
-                .chain(chunk_buffer.into_iter())
-                .collect();
-            chunks.push(Chunk::try_from(all_bytes.as_slice())?);
+        let mut errors = vec![];
+
+        let header_length = Png::STANDARD_HEADER.len();
+        if bytes.len() < header_length || bytes[..header_length] != Png::STANDARD_HEADER {
+            errors.push(ChunkError::new(0, None, "invalid PNG signature".to_string()));
         }
 
+        let mut offset = header_length.min(bytes.len());
+        while offset < bytes.len() {
+            let rest = &bytes[offset..];
+            if chunks.len() >= max_chunks {
+                errors.push(ChunkError::new(offset, None, too_many_chunks(max_chunks)));
+                break;
+            }
+            if rest.len() < 12 {
+                errors.push(ChunkError::new(offset, None, format!("Truncated chunk ({} bytes left)", rest.len())));
+                break;
+            }
 
-        Ok ( Png { chunks } )
+            let length = u32::from_be_bytes([rest[0], rest[1], rest[2], rest[3]]) as usize;
+            // Size of length + size of type + size of data + size of CRC
+            let chunk_size = 4 + 4 + length + 4;
+            if length > Chunk::MAX_LENGTH {
+                let reason = format!("Chunk data length {} exceeds the maximum of {} bytes", length, Chunk::MAX_LENGTH);
+                errors.push(ChunkError::new(offset, None, reason));
+                break;
+            }
+            if chunk_size > rest.len() {
+                errors.push(ChunkError::new(offset, None, format!("Truncated chunk (length {} but {} bytes left)", length, rest.len() - 12)));
+                break;
+            }
+
+            match ChunkType::try_from([rest[4], rest[5], rest[6], rest[7]]) {
+                Ok(chunk_type) => {
+                    let chunk = Chunk::new(chunk_type, rest[8..8 + length].to_vec());
+                    let crc = u32::from_be_bytes(rest[8 + length..chunk_size].try_into().expect("CRC is 4 bytes long"));
+                    if chunk.crc() != crc {
+                        let reason = format!("CRC mismatch (received {}, expected {})", crc, chunk.crc());
+                        errors.push(ChunkError::new(offset, Some(chunks.len()), reason));
+                    }
+                    chunks.push(chunk);
+                },
+                Err(e) => errors.push(ChunkError::new(offset, None, e)),
+            }
+            offset += chunk_size;
+        }
+
+        (Png { chunks }, errors)
+    }
+
+    /// Returns whether both PNGs hold the same chunks (same types and data, with the same number
+    /// of occurrences), regardless of their order. Use `as_bytes` for a strict comparison
+    pub fn equivalent_ignoring_order(&self, other: &Png) -> bool {
+        if self.chunks.len() != other.chunks.len() {
+            return false;
+        }
+
+        let mut counts: HashMap<&Chunk, isize> = HashMap::new();
+        for chunk in &self.chunks {
+            *counts.entry(chunk).or_insert(0) += 1;
+        }
+        for chunk in &other.chunks {
+            *counts.entry(chunk).or_insert(0) -= 1;
+        }
+        counts.values().all(|count| *count == 0)
+    }
+
+    /// Checks the chunk layout required by the PNG spec: exactly one IHDR chunk, which comes first,
+    /// exactly one IEND chunk, which comes last, and no other critical chunk appearing twice.
+    /// IDAT is the only critical chunk allowed to repeat, as the image data can be split into several chunks
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = vec![];
+        let counts = self.count_by_type();
+
+        for (chunk_type, position, expected) in [
+            (ChunkType::IHDR, "first", self.chunks.first()),
+            (ChunkType::IEND, "last", self.chunks.last()),
+        ] {
+            if !counts.contains_key(&chunk_type) {
+                problems.push(format!("missing {} chunk", chunk_type));
+            } else if expected.map(|chunk| chunk.chunk_type()) != Some(&chunk_type) {
+                problems.push(format!("{} chunk is not the {} chunk", chunk_type, position));
+            }
+        }
+        for (chunk_type, count) in &counts {
+            if chunk_type.is_critical() && *count > 1 && chunk_type != &ChunkType::IDAT {
+                problems.push(format!("found {} {} chunks instead of one", count, chunk_type));
+            }
+        }
+
+        if problems.is_empty() { Ok(()) } else { Err(problems) }
+    }
+
+    /// Returns the length of the serialized PNG without serializing it:
+    /// the signature, then the length, type, data and CRC of every chunk
+    pub fn total_size(&self) -> usize {
+        Png::STANDARD_HEADER.len() + self.chunks.iter().map(|chunk| 12 + chunk.data().len()).sum::<usize>()
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut result = vec![];
+        self.write_to(&mut result).expect("writing to a Vec can't fail");
+        result
+    }
+
+    /// Writes the signature and every chunk to the writer, without building the whole file in memory first.
+    /// Pass a `BufWriter` when writing to a file, as every chunk is written with several small writes
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(self.header())?;
+        for chunk in &self.chunks {
+            writer.write_all(&chunk.length().to_be_bytes())?;
+            writer.write_all(&chunk.chunk_type().bytes())?;
+            writer.write_all(chunk.data())?;
+            writer.write_all(&chunk.crc().to_be_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl TryFrom<&[u8]> for Png {
+    type Error = crate::Error;
+    fn try_from(bytes: &[u8]) -> crate::Result<Png> {
+        Png::parse_with_progress(bytes, Png::DEFAULT_MAX_CHUNKS, |_| {})
+    }
+}
+
+/// Joins a run of IDAT chunks holding `length` bytes of data in total into a single chunk, copying the data once
+fn merge_run(mut run: Vec<Chunk>, length: usize) -> Option<Chunk> {
+    if run.len() <= 1 {
+        return run.pop();
+    }
+    let mut data = Vec::with_capacity(length);
+    for chunk in &run {
+        data.extend_from_slice(chunk.data());
+    }
+    Some(Chunk::new(ChunkType::IDAT, data))
+}
+
+fn too_many_chunks(max_chunks: usize) -> String {
+    format!("too many chunks (the limit is {max_chunks})")
+}
 
+/// Fails when a chunk would have to be added to the `count` already parsed ones while `max_chunks` is reached
+fn check_chunk_count(count: usize, max_chunks: usize) -> crate::Result<()> {
+    if count >= max_chunks {
+        Err(PngError::boxed(too_many_chunks(max_chunks)))
+    } else {
+        Ok(())
     }
 }
 
@@ -106,8 +760,28 @@ impl Display for Png {
 }
 
 
+/// Problem found while leniently parsing a PNG file
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChunkError {
+    /// Offset of the chunk (or header) in the file
+    pub offset: usize,
+    /// Index of the chunk in the parsed PNG if it was kept
+    pub index: Option<usize>,
+    pub reason: String,
+}
+impl ChunkError {
+    fn new(offset: usize, index: Option<usize>, reason: String) -> ChunkError {
+        ChunkError { offset, index, reason }
+    }
+}
+impl std::fmt::Display for ChunkError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "at offset {}: {}", self.offset, self.reason)
+    }
+}
+
 #[derive(Debug)]
-struct PngError {
+pub struct PngError {
     reason: String
 }
 impl std::error::Error for PngError {}
@@ -118,7 +792,12 @@ impl std::fmt::Display for PngError {
 }
 impl PngError {
     pub fn boxed(reason: String) -> crate::Error {
-        Box::new(PngError { reason: reason })
+        Box::new(PngError { reason })
+    }
+
+    /// Returns the reason of the failure without the "Png error" prefix
+    pub fn reason(&self) -> &str {
+        &self.reason
     }
 }
 
@@ -126,18 +805,17 @@ impl PngError {
 mod tests {
     use super::*;
     use crate::chunk_type::ChunkType;
-    use crate::chunk::Chunk;
+    use crate::chunk::{Chunk, ChunkDecodingError};
     use std::str::FromStr;
     use std::convert::TryFrom;
+    use std::io::Cursor;
 
     fn testing_chunks() -> Vec<Chunk> {
-        let mut chunks = Vec::new();
-
-        chunks.push(chunk_from_strings("FrSt", "I am the first chunk").unwrap());
-        chunks.push(chunk_from_strings("miDl", "I am another chunk").unwrap());
-        chunks.push(chunk_from_strings("LASt", "I am the last chunk").unwrap());
-
-        chunks
+        vec![
+            chunk_from_strings("FrSt", "I am the first chunk").unwrap(),
+            chunk_from_strings("miDl", "I am another chunk").unwrap(),
+            chunk_from_strings("LASt", "I am the last chunk").unwrap(),
+        ]
     }
 
     fn testing_png() -> Png {
@@ -161,46 +839,261 @@ mod tests {
     }
 
     #[test]
-    fn test_valid_from_bytes() {
-        let chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+    fn test_append_chunk_before_iend() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::IEND, vec![]));
+        png.append_chunk(chunk_from_strings("TeSt", "Message").unwrap());
 
-        let bytes: Vec<u8> = Png::STANDARD_HEADER
-            .iter()
-            .chain(chunk_bytes.iter())
-            .copied()
-            .collect();
+        let types: Vec<&str> = png.chunks().iter().map(|chunk| chunk.chunk_type().as_str()).collect();
+        assert_eq!(types, ["FrSt", "miDl", "LASt", "TeSt", "IEND"]);
+    }
 
-        let png = Png::try_from(bytes.as_ref());
+    #[test]
+    fn test_insert_chunk() {
+        let mut png = testing_png();
+        png.insert_chunk(1, chunk_from_strings("InSt", "I am inserted").unwrap()).unwrap();
 
-        assert!(png.is_ok());
+        let types: Vec<&str> = png.chunks().iter().map(|chunk| chunk.chunk_type().as_str()).collect();
+        assert_eq!(types, ["FrSt", "InSt", "miDl", "LASt"]);
     }
 
     #[test]
-    fn test_invalid_header() {
-        let chunk_bytes: Vec<u8> = testing_chunks()
-            .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
-            .collect();
+    fn test_insert_chunk_out_of_bounds() {
+        let mut png = testing_png();
+        png.insert_chunk(3, chunk_from_strings("EnDs", "I am last").unwrap()).unwrap();
+        assert_eq!(png.chunks()[3].chunk_type().as_str(), "EnDs");
 
-        let bytes: Vec<u8> = [13, 80, 78, 71, 13, 10, 26, 10]
-            .iter()
-            .chain(chunk_bytes.iter())
-            .copied()
-            .collect();
+        assert!(png.insert_chunk(5, chunk_from_strings("OuTs", "I don't fit").unwrap()).is_err());
+        assert_eq!(png.chunks().len(), 4);
+    }
 
-        let png = Png::try_from(bytes.as_ref());
+    #[test]
+    fn test_find() {
+        let png = testing_png();
+        let found = png.find(b"another");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].0, 1);
+        assert_eq!(found[0].1.chunk_type().as_str(), "miDl");
 
-        assert!(png.is_err());
+        assert_eq!(png.find(b"chunk").len(), 3);
+        assert!(png.find(b"CHUNK").is_empty());
+        assert_eq!(png.find_ignore_case(b"CHUNK").len(), 3);
+        assert!(png.find(b"secret").is_empty());
     }
 
     #[test]
-    fn test_invalid_chunk() {
-        let mut chunk_bytes: Vec<u8> = testing_chunks()
+    fn test_count_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a duplicated chunk").unwrap());
+        png.append_chunk(chunk_from_strings("miDl", "So am I").unwrap());
+
+        let counts: Vec<(String, usize)> = png
+            .count_by_type()
             .into_iter()
-            .flat_map(|chunk| chunk.as_bytes())
+            .map(|(chunk_type, count)| (chunk_type.to_string(), count))
+            .collect();
+        assert_eq!(counts, [("FrSt".to_string(), 1), ("LASt".to_string(), 1), ("miDl".to_string(), 3)]);
+        assert!(Png::from_chunks(vec![]).count_by_type().is_empty());
+    }
+
+    fn valid_png() -> Png {
+        Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "first half").unwrap(),
+            chunk_from_strings("IDAT", "second half").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ])
+    }
+
+    #[test]
+    fn test_parse_chunks_with_progress() {
+        let png = testing_png();
+        let bytes = png.as_bytes();
+        let mut offsets = vec![];
+
+        let (chunks, trailing) = Png::parse_chunks_with_progress(&bytes[8..], |consumed| offsets.push(consumed)).unwrap();
+        assert_eq!(chunks, png.chunks());
+        assert!(trailing.is_empty());
+        assert_eq!(offsets, [32, 62, 93]);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(valid_png().validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_validate_duplicate_ihdr() {
+        let mut png = valid_png();
+        png.insert_chunk(2, chunk_from_strings("IHDR", "header").unwrap()).unwrap();
+        assert_eq!(png.validate(), Err(vec!["found 2 IHDR chunks instead of one".to_string()]));
+    }
+
+    #[test]
+    fn test_validate_missing_iend() {
+        let mut png = valid_png();
+        png.remove_chunk_by_index(3).unwrap();
+        assert_eq!(png.validate(), Err(vec!["missing IEND chunk".to_string()]));
+    }
+
+    #[test]
+    fn test_validate_misplaced_chunks() {
+        let mut png = valid_png();
+        png.insert_chunk(0, chunk_from_strings("PLTE", "palette").unwrap()).unwrap();
+        png.insert_chunk(5, chunk_from_strings("PLTE", "palette").unwrap()).unwrap();
+        assert_eq!(png.validate(), Err(vec![
+            "IHDR chunk is not the first chunk".to_string(),
+            "IEND chunk is not the last chunk".to_string(),
+            "found 2 PLTE chunks instead of one".to_string(),
+        ]));
+    }
+
+    #[test]
+    fn test_diff_added_chunk() {
+        let png = testing_png();
+        let mut other = testing_png();
+        other.insert_chunk(1, chunk_from_strings("miDl", "I am new").unwrap()).unwrap();
+        other.append_chunk(chunk_from_strings("AdDs", "Me too").unwrap());
+
+        let diffs = png.diff(&other);
+        let midl = ChunkType::from_str("miDl").unwrap();
+        assert_eq!(diffs, [
+            ChunkDiff::Changed { index: 1, other_index: 1, chunk_type: midl },
+            ChunkDiff::Added { index: 2, chunk_type: midl },
+            ChunkDiff::Added { index: 4, chunk_type: ChunkType::from_str("AdDs").unwrap() },
+        ]);
+        assert!(png.diff(&testing_png()).is_empty());
+    }
+
+    #[test]
+    fn test_diff_changed_and_removed_chunks() {
+        let png = testing_png();
+        let mut chunks = testing_chunks();
+        chunks[0].set_data(b"I am a modified chunk".to_vec());
+        chunks.pop();
+        let other = Png::from_chunks(chunks);
+
+        assert_eq!(png.diff(&other), [
+            ChunkDiff::Changed { index: 0, other_index: 0, chunk_type: ChunkType::from_str("FrSt").unwrap() },
+            ChunkDiff::Removed { index: 2, chunk_type: ChunkType::from_str("LASt").unwrap() },
+        ]);
+    }
+
+    #[test]
+    fn test_chunk_by_index() {
+        let png = testing_png();
+        assert_eq!(png.chunk_by_index(0).unwrap().chunk_type().as_str(), "FrSt");
+        assert_eq!(png.chunk_by_index(2).unwrap().data_as_string().unwrap(), "I am the last chunk");
+    }
+
+    #[test]
+    fn test_chunk_by_index_out_of_range() {
+        let png = testing_png();
+        assert!(png.chunk_by_index(3).is_none());
+        assert!(png.chunk_by_index(usize::MAX).is_none());
+    }
+
+    #[test]
+    fn test_len() {
+        let png = testing_png();
+        assert_eq!(png.len(), 3);
+        assert_eq!(png.chunks().iter().count(), png.len());
+        assert_eq!(png.chunks().iter().filter(|chunk| chunk.chunk_type().is_critical()).count(), 2);
+
+        assert!(!png.is_empty());
+        assert!(Png::from_chunks(vec![]).is_empty());
+    }
+
+    #[test]
+    fn test_valid_from_bytes() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png = Png::try_from(bytes.as_ref());
+
+        assert!(png.is_ok());
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .collect();
+
+        let bytes: Vec<u8> = [13, 80, 78, 71, 13, 10, 26, 10]
+            .iter()
+            .chain(chunk_bytes.iter())
+            .copied()
+            .collect();
+
+        let png = Png::try_from(bytes.as_ref());
+
+        assert!(png.is_err());
+    }
+
+    #[test]
+    fn test_corrupted_signature_error() {
+        let mut bytes = testing_png().as_bytes();
+        bytes[0] = 0;
+
+        let error = Png::try_from(bytes.as_ref()).err().unwrap();
+        assert!(error.to_string().contains("invalid PNG signature"));
+
+        let png = Png::try_from(&Png::STANDARD_HEADER[..4]);
+        assert!(png.err().unwrap().to_string().contains("invalid PNG signature"));
+    }
+
+    #[test]
+    fn test_valid_signature_round_trip() {
+        let bytes = Png::STANDARD_HEADER.to_vec();
+        let png = Png::try_from(bytes.as_ref()).unwrap();
+
+        assert!(png.chunks().is_empty());
+        assert_eq!(png.header(), &Png::STANDARD_HEADER);
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_forced_signature() {
+        let mut bytes = testing_png().as_bytes();
+        bytes[0] = 0;
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+        let png = Png::try_from_forced_signature(&bytes).unwrap();
+        assert_eq!(png.chunks().len(), 3);
+        assert!(Png::try_from_forced_signature(&bytes[..5]).is_err());
+    }
+
+    #[test]
+    fn test_error_offset() {
+        let mut bytes = testing_png().as_bytes();
+        // Corrupt the CRC of the second chunk, which starts after the signature and the 32 bytes of the first chunk
+        bytes[8 + 32 + 29] ^= 1;
+
+        let error = Png::try_from(bytes.as_slice()).err().unwrap();
+        assert_eq!(error.downcast_ref::<ChunkDecodingError>().unwrap().offset(), Some(40));
+        assert!(error.to_string().starts_with("Bad chunk at offset 40: CRC mismatch"));
+
+        let mut bytes = testing_png().as_bytes();
+        bytes.extend_from_slice(&[0, 0]);
+        let error = Png::try_from(bytes.as_slice()).err().unwrap();
+        assert_eq!(error.to_string(), "Png error: 2 trailing bytes after the last chunk at offset 101");
+    }
+
+    #[test]
+    fn test_invalid_chunk() {
+        let mut chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
             .collect();
 
         #[rustfmt::skip]
@@ -219,6 +1112,113 @@ mod tests {
     }
 
 
+    #[test]
+    fn test_parse_chunks_trailing_bytes() {
+        let chunk_bytes: Vec<u8> = testing_chunks()
+            .into_iter()
+            .flat_map(|chunk| chunk.as_bytes())
+            .chain([1, 2, 3, 4, 5])
+            .collect();
+
+        let (chunks, trailing) = Png::parse_chunks(&chunk_bytes).unwrap();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(trailing, &[1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn test_parse_chunks_truncated_chunk() {
+        let truncated = testing_chunks()[0].as_bytes();
+        let truncated = &truncated[..truncated.len() - 1];
+        let chunk_bytes: Vec<u8> = testing_chunks()[1]
+            .as_bytes()
+            .into_iter()
+            .chain(truncated.iter().copied())
+            .collect();
+
+        let (chunks, trailing) = Png::parse_chunks(&chunk_bytes).unwrap();
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(trailing, truncated);
+    }
+
+    #[test]
+    fn test_parse_chunks_length_over_spec_max() {
+        let chunk_bytes: Vec<u8> = 0x8000_0000u32
+            .to_be_bytes()
+            .iter()
+            .chain(b"RuSt")
+            .copied()
+            .collect();
+
+        assert!(Png::parse_chunks(&chunk_bytes).is_err());
+    }
+
+    #[test]
+    fn test_trailing_bytes_rejected() {
+        let bytes: Vec<u8> = Png::STANDARD_HEADER
+            .iter()
+            .copied()
+            .chain(testing_chunks().into_iter().flat_map(|chunk| chunk.as_bytes()))
+            .chain([1, 2, 3])
+            .collect();
+
+        assert!(Png::try_from(bytes.as_ref()).is_err());
+    }
+
+
+    fn corrupted_png_bytes() -> Vec<u8> {
+        let mut bytes = Png::from_chunks(testing_chunks()).as_bytes();
+        // Last byte of the second chunk's CRC: header + first chunk + second chunk - 1
+        let offset = 8 + (12 + 20) + (12 + 18) - 1;
+        bytes[offset] ^= 1;
+        bytes
+    }
+
+    #[test]
+    fn test_try_from_lenient_valid() {
+        let bytes = testing_png().as_bytes();
+        let (png, errors) = Png::try_from_lenient(&bytes);
+        assert!(errors.is_empty());
+        assert_eq!(png.as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_try_from_lenient_crc_mismatch() {
+        let (png, errors) = Png::try_from_lenient(&corrupted_png_bytes());
+        assert_eq!(png.chunks().len(), 3);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].offset, 8 + 12 + 20);
+        assert_eq!(errors[0].index, Some(1));
+        assert!(errors[0].reason.starts_with("CRC mismatch"));
+    }
+
+    #[test]
+    fn test_try_from_lenient_bad_chunks() {
+        let mut bytes = testing_png().as_bytes();
+        // Invalid type for the first chunk and truncated last chunk
+        bytes[12] = b'1';
+        bytes.pop();
+
+        let (png, errors) = Png::try_from_lenient(&bytes);
+        assert_eq!(png.chunks().len(), 1);
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].offset, 8);
+        assert_eq!(errors[0].index, None);
+        assert_eq!(errors[1].offset, 8 + 32 + 30);
+        assert!(errors[1].reason.starts_with("Truncated chunk"));
+    }
+
+    #[test]
+    fn test_try_from_lenient_oversized_length() {
+        let mut bytes = testing_png().as_bytes();
+        bytes[8..12].copy_from_slice(&u32::MAX.to_be_bytes());
+
+        let (png, errors) = Png::try_from_lenient(&bytes);
+        assert!(png.chunks().is_empty());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].offset, 8);
+        assert_eq!(errors[0].reason, format!("Chunk data length {} exceeds the maximum of {} bytes", u32::MAX, Chunk::MAX_LENGTH));
+    }
+
     #[test]
     fn test_list_chunks() {
         let png = testing_png();
@@ -253,10 +1253,215 @@ mod tests {
         assert!(chunk.is_none());
     }
 
+    #[test]
+    fn test_rename_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "Keep me").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "Not me").unwrap());
+        png.rename_chunk("ruSt".parse().unwrap(), "teSt".parse().unwrap()).unwrap();
+
+        let renamed = png.chunk_by_type("teSt".parse().unwrap()).unwrap();
+        assert_eq!(renamed.data_as_string().unwrap(), "Keep me");
+        assert_eq!(renamed, &chunk_from_strings("teSt", "Keep me").unwrap());
+        assert_eq!(png.chunks_by_type("ruSt".parse().unwrap()).len(), 1);
+        assert_eq!(png.len(), 5);
+    }
+
+    #[test]
+    fn test_rename_missing_chunk() {
+        let mut png = testing_png();
+        assert!(png.rename_chunk("ruSt".parse().unwrap(), "teSt".parse().unwrap()).is_err());
+        assert_eq!(png.chunks(), testing_png().chunks());
+    }
+
+    #[test]
+    fn test_text_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::text("Author", "Jane Doe").unwrap());
+        png.append_chunk(chunk_from_strings("tEXt", "no separator").unwrap());
+        png.append_chunk(Chunk::text("Title", "Dice").unwrap());
+        png.append_chunk(Chunk::new(ChunkType::from_str("zTXt").unwrap(), text::encode_ztxt("Comment", "Compressed").unwrap()));
+        png.append_chunk(chunk_from_strings("zTXt", "Comment\0\u{1}not zlib").unwrap());
+
+        assert_eq!(png.text_chunks(), [
+            ("Author".to_string(), "Jane Doe".to_string()),
+            ("Title".to_string(), "Dice".to_string()),
+            ("Comment".to_string(), "Compressed".to_string()),
+        ]);
+        assert!(testing_png().text_chunks().is_empty());
+    }
+
+    #[test]
+    fn test_dedup_chunks() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "Hidden").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "Other").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "Hidden").unwrap());
+        png.append_chunk(chunk_from_strings("miDl", "I am another chunk").unwrap());
+
+        assert_eq!(png.dedup_chunks(), 2);
+        let data: Vec<String> = png.chunks().iter().map(|chunk| chunk.data_as_string().unwrap()).collect();
+        assert_eq!(data, ["I am the first chunk", "I am another chunk", "I am the last chunk", "Hidden", "Other"]);
+        assert_eq!(png.dedup_chunks(), 0);
+    }
+
+    #[test]
+    fn test_dedup_keeps_critical_chunks() {
+        let idat = chunk_from_strings("IDAT", "same image data").unwrap();
+        let mut png = Png::from_chunks(vec![idat.clone(), idat]);
+        assert_eq!(png.dedup_chunks(), 0);
+        assert_eq!(png.len(), 2);
+    }
+
+    #[test]
+    fn test_remove_protected_chunk() {
+        let mut png = testing_png();
+        png.append_chunk(Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![]));
+
+        let error = png.remove_chunk(ChunkType::from_str("IEND").unwrap()).unwrap_err();
+        assert_eq!(error.downcast_ref::<PngError>().unwrap().reason(), "refusing to remove critical chunk IEND");
+        assert_eq!(png.chunks().len(), 4);
+
+        png.force_remove_chunk(ChunkType::from_str("IEND").unwrap()).unwrap();
+        assert!(png.chunk_by_type(ChunkType::from_str("IEND").unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_remove_chunk_by_index() {
+        let mut png = testing_png();
+        let chunk = png.remove_chunk_by_index(1).unwrap();
+        assert_eq!(chunk.chunk_type().as_str(), "miDl");
+
+        let types: Vec<&str> = png.chunks().iter().map(|chunk| chunk.chunk_type().as_str()).collect();
+        assert_eq!(types, ["FrSt", "LASt"]);
+    }
+
+    #[test]
+    fn test_remove_chunk_by_index_out_of_range() {
+        let mut png = testing_png();
+        assert!(png.remove_chunk_by_index(3).is_err());
+        assert_eq!(png.len(), 3);
+    }
+
+    #[test]
+    fn test_chunk_at_mut() {
+        let mut png = testing_png();
+        png.chunk_at_mut(1).unwrap().set_data(b"I am an edited chunk".to_vec());
+
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "I am an edited chunk");
+        assert_eq!(&png.chunks()[0].data_as_string().unwrap(), "I am the first chunk");
+    }
+
+    #[test]
+    fn test_chunk_at_mut_out_of_range() {
+        let mut png = testing_png();
+        assert!(png.chunk_at_mut(3).is_none());
+    }
+
+    fn ihdr_chunk(color_type: u8) -> Chunk {
+        let data = vec![0, 0, 0, 1, 0, 0, 0, 1, 8, color_type, 0, 0, 0];
+        Chunk::new(ChunkType::from_str("IHDR").unwrap(), data)
+    }
+
+    #[test]
+    fn test_background_palette() {
+        let png = Png::from_chunks(vec![
+            ihdr_chunk(3),
+            Chunk::new(ChunkType::from_str("bKGD").unwrap(), vec![7]),
+        ]);
+        assert_eq!(png.background().unwrap(), Some(Background::PaletteIndex(7)));
+    }
+
+    #[test]
+    fn test_background_rgb() {
+        let png = Png::from_chunks(vec![
+            ihdr_chunk(2),
+            Chunk::new(ChunkType::from_str("bKGD").unwrap(), vec![0, 10, 0, 20, 0, 30]),
+        ]);
+        assert_eq!(png.background().unwrap(), Some(Background::Rgb(10, 20, 30)));
+    }
+
+    #[test]
+    fn test_background_missing() {
+        let png = Png::from_chunks(vec![ihdr_chunk(2)]);
+        assert_eq!(png.background().unwrap(), None);
+
+        let png = Png::from_chunks(vec![Chunk::new(ChunkType::from_str("bKGD").unwrap(), vec![7])]);
+        assert!(png.background().is_err());
+    }
+
+    fn png_from_types(types: &[&str]) -> Png {
+        Png::from_chunks(types.iter().map(|chunk_type| chunk_from_strings(chunk_type, "").unwrap()).collect())
+    }
+
+    fn report_as_strings(png: &Png) -> Vec<(PlacementGroup, Vec<String>)> {
+        png.placement_report()
+            .into_iter()
+            .map(|(group, types)| (group, types.iter().map(|chunk_type| chunk_type.to_string()).collect()))
+            .collect()
+    }
+
+    #[test]
+    fn test_placement_report_with_plte() {
+        let png = png_from_types(&["IHDR", "gAMA", "PLTE", "tRNS", "IDAT", "IDAT", "tEXt", "IEND"]);
+        let report = report_as_strings(&png);
+        assert_eq!(report, vec![
+            (PlacementGroup::BeforePlte, vec!["IHDR".to_string(), "gAMA".to_string()]),
+            (PlacementGroup::Plte, vec!["PLTE".to_string()]),
+            (PlacementGroup::BeforeIdat, vec!["tRNS".to_string()]),
+            (PlacementGroup::Idat, vec!["IDAT".to_string(), "IDAT".to_string()]),
+            (PlacementGroup::AfterIdat, vec!["tEXt".to_string(), "IEND".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn test_placement_report_without_plte() {
+        let png = png_from_types(&["IHDR", "gAMA", "IDAT", "ruSt", "IDAT", "IEND"]);
+        let report = report_as_strings(&png);
+        assert_eq!(report, vec![
+            (PlacementGroup::BeforePlte, vec![]),
+            (PlacementGroup::Plte, vec![]),
+            (PlacementGroup::BeforeIdat, vec!["IHDR".to_string(), "gAMA".to_string()]),
+            (PlacementGroup::Idat, vec!["IDAT".to_string(), "IDAT".to_string()]),
+            (PlacementGroup::AfterIdat, vec!["ruSt".to_string(), "IEND".to_string()]),
+        ]);
+    }
+
+    #[test]
+    fn test_retain_critical() {
+        let mut png = testing_png();
+        let removed = png.retain_critical();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(&removed[0].chunk_type().to_string(), "miDl");
+        assert_eq!(png.chunks().len(), 2);
+        assert!(png.chunks().iter().all(|chunk| chunk.chunk_type().is_critical()));
+    }
+
+    #[test]
+    fn test_replace_all() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am a duplicated chunk").unwrap());
+
+        let replaced = png.replace_all("miDl".parse().unwrap(), b"Watermark".to_vec());
+        assert_eq!(replaced, 2);
+
+        let png = Png::try_from(png.as_bytes().as_slice()).unwrap();
+        assert_eq!(&png.chunks()[1].data_as_string().unwrap(), "Watermark");
+        assert_eq!(&png.chunks()[3].data_as_string().unwrap(), "Watermark");
+        assert_eq!(&png.chunks()[0].data_as_string().unwrap(), "I am the first chunk");
+    }
+
+    #[test]
+    fn test_replace_all_missing_type() {
+        let mut png = testing_png();
+        assert_eq!(png.replace_all("TeSt".parse().unwrap(), vec![]), 0);
+    }
+
     #[test]
     fn test_png_from_image_file() {
         let png = Png::try_from(&PNG_FILE[..]);
-        println!("{}", png.unwrap().to_string());
+        println!("{}", png.unwrap());
         // assert!(png.is_ok());
     }
 
@@ -268,6 +1473,61 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn test_from_reader() {
+        let png = Png::from_reader(Cursor::new(&PNG_FILE[..])).unwrap();
+        assert_eq!(png.chunks(), Png::try_from(&PNG_FILE[..]).unwrap().chunks());
+        assert!(Png::from_reader(Cursor::new(Png::STANDARD_HEADER)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_from_reader_invalid() {
+        let bytes = testing_png().as_bytes();
+        assert!(Png::from_reader(Cursor::new(&bytes[..bytes.len() - 1])).is_err());
+        assert!(Png::from_reader(Cursor::new(&bytes[..bytes.len() - 30])).is_err());
+        let error = Png::from_reader(Cursor::new(&bytes[..4])).err().unwrap();
+        assert_eq!(error.to_string(), "Png error: invalid PNG signature (only 4 bytes)");
+
+        struct FailingReader;
+        impl Read for FailingReader {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("device not ready"))
+            }
+        }
+        assert_eq!(Png::from_reader(FailingReader).err().unwrap().to_string(), "device not ready");
+
+        let mut corrupted = bytes.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 1;
+        assert!(Png::from_reader(Cursor::new(corrupted)).is_err());
+    }
+
+    #[test]
+    fn test_max_chunks() {
+        let bytes = testing_png().as_bytes();
+        assert_eq!(Png::try_from_with_limit(&bytes, 3).unwrap().len(), 3);
+        assert_eq!(Png::from_reader_with_limit(Cursor::new(&bytes), 3).unwrap().len(), 3);
+
+        let error = Png::try_from_with_limit(&bytes, 2).err().unwrap();
+        assert_eq!(error.to_string(), "Png error: too many chunks (the limit is 2)");
+        assert!(Png::from_reader_with_limit(Cursor::new(&bytes), 2).is_err());
+        assert!(Png::try_from_with_limit(&bytes, 0).is_err());
+        assert!(Png::try_from_forced_signature_with_limit(&bytes, 2).is_err());
+
+        let (png, errors) = Png::try_from_lenient_with_limit(&bytes, 2);
+        assert_eq!(png.len(), 2);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].reason, "too many chunks (the limit is 2)");
+    }
+
+    #[test]
+    fn test_total_size() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.total_size(), png.as_bytes().len());
+        assert_eq!(testing_png().total_size(), testing_png().as_bytes().len());
+        assert_eq!(Png::from_chunks(vec![]).total_size(), 8);
+    }
+
     #[test]
     fn test_png_trait_impls() {
         let chunk_bytes: Vec<u8> = testing_chunks()
@@ -286,6 +1546,264 @@ mod tests {
         let _png_string = format!("{}", png);
     }
 
+    #[test]
+    fn test_chunks_by_type() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "first message").unwrap());
+        png.append_chunk(chunk_from_strings("ruSt", "second message").unwrap());
+
+        let chunks = png.chunks_by_type(ChunkType::from_str("ruSt").unwrap());
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[0].data(), b"first message");
+        assert_eq!(chunks[1].data(), b"second message");
+        assert!(png.chunks_by_type(ChunkType::from_str("abCd").unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_into_chunks() {
+        let png = testing_png();
+        let bytes = png.as_bytes();
+        let chunks = png.into_chunks();
+        assert_eq!(chunks.len(), 3);
+        assert_eq!(Png::from_chunks(chunks).as_bytes(), bytes);
+    }
+
+    #[test]
+    fn test_unused_private_type() {
+        let chunk_type = testing_png().unused_private_type().unwrap();
+        assert_eq!(chunk_type.to_string(), "ruSt");
+        assert!(!chunk_type.is_critical());
+        assert!(!chunk_type.is_public());
+        assert!(chunk_type.is_reserved_bit_valid());
+        assert!(chunk_type.is_safe_to_copy());
+
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("ruSt", "taken").unwrap());
+        png.append_chunk(chunk_from_strings("ruAa", "taken").unwrap());
+        assert_eq!(png.unused_private_type().unwrap().to_string(), "ruAb");
+    }
+
+    #[test]
+    fn test_build_type_index() {
+        let mut png = testing_png();
+        png.append_chunk(chunk_from_strings("miDl", "I am yet another chunk").unwrap());
+        let index = png.build_type_index();
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index[b"FrSt"], vec![0]);
+        assert_eq!(index[b"miDl"], vec![1, 3]);
+        assert!(!index.contains_key(b"ruSt"));
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture bench_`
+    #[test]
+    #[ignore]
+    fn bench_type_index_lookup() {
+        let chunks = (0..10_000u32)
+            .map(|i| {
+                let code = [b'a' + (i % 26) as u8, b'a' + (i / 26 % 26) as u8, b'A' + (i / 676 % 26) as u8, b'a'];
+                Chunk::new(ChunkType::try_from(code).unwrap(), i.to_be_bytes().to_vec())
+            })
+            .collect();
+        let png = Png::from_chunks(chunks);
+        let lookups: Vec<ChunkType> = png.chunks().iter().rev().step_by(10).map(|chunk| *chunk.chunk_type()).collect();
+
+        let start = std::time::Instant::now();
+        let linear: Vec<&[u8]> = lookups.iter().map(|chunk_type| png.chunk_by_type(*chunk_type).unwrap().data()).collect();
+        let linear_time = start.elapsed();
+
+        let start = std::time::Instant::now();
+        let index = png.build_type_index();
+        let indexed: Vec<&[u8]> = lookups.iter().map(|chunk_type| png.chunks()[index[&chunk_type.bytes()][0]].data()).collect();
+        let indexed_time = start.elapsed();
+
+        assert_eq!(linear, indexed);
+        println!("{} lookups, linear: {:?}, indexed (with building): {:?}", lookups.len(), linear_time, indexed_time);
+    }
+
+    #[test]
+    fn test_fix_case() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("ihdr", "header").unwrap(),
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("ruSt", "custom").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        let expected_crc = chunk_from_strings("IHDR", "header").unwrap().crc();
+
+        let corrected = png.fix_case();
+        assert_eq!(corrected, vec![(0, ChunkType::from_str("ihdr").unwrap())]);
+        assert_eq!(&png.chunks()[0].chunk_type().to_string(), "IHDR");
+        assert_eq!(png.chunks()[0].crc(), expected_crc);
+        assert_eq!(&png.chunks()[2].chunk_type().to_string(), "ruSt");
+        assert!(png.fix_case().is_empty());
+    }
+
+    #[test]
+    fn test_set_exif() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "data").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        assert_eq!(png.exif(), None);
+
+        png.set_exif(b"II*\0first".to_vec()).unwrap();
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "eXIf");
+        assert_eq!(png.exif(), Some(&b"II*\0first"[..]));
+
+        png.set_exif(b"MM\0*second".to_vec()).unwrap();
+        assert_eq!(png.chunks().len(), 4);
+        assert_eq!(png.exif(), Some(&b"MM\0*second"[..]));
+    }
+
+    #[test]
+    fn test_idat_stats() {
+        let png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "first").unwrap(),
+            chunk_from_strings("IDAT", "second").unwrap(),
+            chunk_from_strings("IDAT", "third").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        assert_eq!(png.idat_stats(), (3, 16));
+        assert_eq!(testing_png().idat_stats(), (0, 0));
+    }
+
+    #[test]
+    fn test_merge_idat() {
+        let mut png = Png::from_chunks(vec![
+            chunk_from_strings("IHDR", "header").unwrap(),
+            chunk_from_strings("IDAT", "first").unwrap(),
+            chunk_from_strings("IDAT", "second").unwrap(),
+            chunk_from_strings("tEXt", "comment").unwrap(),
+            chunk_from_strings("IDAT", "third").unwrap(),
+            chunk_from_strings("IDAT", "fourth").unwrap(),
+            chunk_from_strings("IEND", "").unwrap(),
+        ]);
+        let payload: Vec<u8> = png.chunks().iter()
+            .filter(|chunk| &chunk.chunk_type().bytes() == b"IDAT")
+            .flat_map(|chunk| chunk.data().to_vec())
+            .collect();
+        let size_before = png.as_bytes().len();
+
+        assert_eq!(png.merge_idat(), 2);
+        assert_eq!(png.chunks().len(), 5);
+        assert_eq!(png.chunks()[1].data(), b"firstsecond");
+        assert_eq!(png.chunks()[3].data(), b"thirdfourth");
+        assert_eq!(png.as_bytes().len(), size_before - 24);
+
+        let merged_payload: Vec<u8> = png.chunks().iter()
+            .filter(|chunk| &chunk.chunk_type().bytes() == b"IDAT")
+            .flat_map(|chunk| chunk.data().to_vec())
+            .collect();
+        assert_eq!(merged_payload, payload);
+
+        let bytes = png.as_bytes();
+        assert!(Png::try_from(bytes.as_slice()).is_ok());
+    }
+
+    #[test]
+    fn test_merge_many_idat() {
+        let idat: Vec<Chunk> = (0..1000u32).map(|index| Chunk::new(ChunkType::IDAT, index.to_be_bytes().to_vec())).collect();
+        let payload: Vec<u8> = idat.iter().flat_map(|chunk| chunk.data().to_vec()).collect();
+        let mut png = Png::from_chunks(idat);
+
+        assert_eq!(png.merge_idat(), 999);
+        assert_eq!(png.chunks()[0].data(), payload.as_slice());
+        assert_eq!(png.merge_idat(), 0);
+    }
+
+    #[test]
+    fn test_equivalent_ignoring_order() {
+        let png = testing_png();
+        let mut chunks = testing_chunks();
+        chunks.reverse();
+        let reordered = Png::from_chunks(chunks);
+
+        assert!(png.equivalent_ignoring_order(&reordered));
+        assert_ne!(png.as_bytes(), reordered.as_bytes());
+    }
+
+    #[test]
+    fn test_not_equivalent_ignoring_order() {
+        let png = testing_png();
+
+        let mut chunks = testing_chunks();
+        chunks[0] = chunk_from_strings("FrSt", "I am a different chunk").unwrap();
+        assert!(!png.equivalent_ignoring_order(&Png::from_chunks(chunks)));
+
+        let mut chunks = testing_chunks();
+        chunks[2] = chunk_from_strings("FrSt", "I am the first chunk").unwrap();
+        assert!(!png.equivalent_ignoring_order(&Png::from_chunks(chunks)));
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        assert_eq!(png.dimensions(), Some((50, 50)));
+
+        let ihdr = png.ihdr().unwrap();
+        assert_eq!(ihdr.bit_depth(), 8);
+        assert_eq!(ihdr.color_type().unwrap(), crate::ihdr::ColorType::Rgba);
+    }
+
+    #[test]
+    fn test_dimensions_without_ihdr() {
+        assert_eq!(testing_png().dimensions(), None);
+        assert_eq!(Png::from_chunks(vec![]).dimensions(), None);
+
+        let malformed = Png::from_chunks(vec![Chunk::new(ChunkType::from_str("IHDR").unwrap(), vec![0; 5])]);
+        assert_eq!(malformed.dimensions(), None);
+        assert!(malformed.ihdr().is_err());
+    }
+
+    #[test]
+    fn test_try_from_path() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), PNG_FILE).unwrap();
+
+        let png = Png::try_from_path(file.path()).unwrap();
+        assert_eq!(png.as_bytes(), PNG_FILE);
+    }
+
+    #[test]
+    fn test_try_from_path_invalid() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        assert!(Png::try_from_path(file.path()).is_err());
+        assert!(Png::try_from_path(Path::new("/nonexistent/file.png")).is_err());
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture bench_`
+    #[test]
+    #[ignore]
+    fn bench_try_from_path() {
+        let idat = Chunk::new(ChunkType::from_str("IDAT").unwrap(), vec![42; 256 * 1024 * 1024]);
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), Png::from_chunks(vec![idat]).as_bytes()).unwrap();
+
+        let start = std::time::Instant::now();
+        let read = Png::try_from(std::fs::read(file.path()).unwrap().as_slice()).unwrap();
+        let read_time = start.elapsed();
+        drop(read);
+
+        let start = std::time::Instant::now();
+        let mapped = Png::try_from_path(file.path()).unwrap();
+        let mapped_time = start.elapsed();
+
+        assert_eq!(mapped.len(), 1);
+        println!("fs::read + try_from: {:?}, try_from_path: {:?} (saves a 256 MiB heap copy)", read_time, mapped_time);
+    }
+
+    #[test]
+    fn test_write_to() {
+        let png = Png::try_from(&PNG_FILE[..]).unwrap();
+        let mut written = vec![];
+        png.write_to(&mut written).unwrap();
+        assert_eq!(written, png.as_bytes());
+        assert_eq!(written, PNG_FILE);
+    }
+
     // This is the raw bytes for a shrunken version of the `dice.png` image on Wikipedia
     const PNG_FILE: [u8; 4803] = [
         137, 80, 78, 71, 13, 10, 26, 10, 0, 0, 0, 13, 73, 72, 68, 82, 0, 0, 0, 50, 0, 0, 0, 50, 8,
@@ -0,0 +1,53 @@
+/// Known file signatures and the name of the file type they start
+const SIGNATURES: [(&[u8], &str); 5] = [
+    (&[137, 80, 78, 71, 13, 10, 26, 10], "PNG"),
+    (&[0xFF, 0xD8, 0xFF], "JPEG"),
+    (b"PK\x03\x04", "ZIP"),
+    (b"%PDF-", "PDF"),
+    (&[0x1F, 0x8B], "gzip"),
+];
+
+/// Returns the file type whose magic bytes start the data, or "unknown"
+pub fn sniff(data: &[u8]) -> &'static str {
+    SIGNATURES
+        .iter()
+        .find(|(signature, _)| data.starts_with(signature))
+        .map_or("unknown", |(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk::Chunk;
+    use crate::chunk_type::ChunkType;
+    use crate::png::Png;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_sniff_embedded_png() {
+        let png = Png::from_chunks(vec![Chunk::new(ChunkType::from_str("IEND").unwrap(), vec![])]);
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), png.as_bytes());
+        assert_eq!(sniff(chunk.data()), "PNG");
+    }
+
+    #[test]
+    fn test_sniff_embedded_zip() {
+        let zip = b"PK\x03\x04\x14\x00\x00\x00\x08\x00".to_vec();
+        let chunk = Chunk::new(ChunkType::from_str("ruSt").unwrap(), zip);
+        assert_eq!(sniff(chunk.data()), "ZIP");
+    }
+
+    #[test]
+    fn test_sniff_other_types() {
+        assert_eq!(sniff(&[0xFF, 0xD8, 0xFF, 0xE0]), "JPEG");
+        assert_eq!(sniff(b"%PDF-1.7"), "PDF");
+        assert_eq!(sniff(&[0x1F, 0x8B, 0x08]), "gzip");
+    }
+
+    #[test]
+    fn test_sniff_unknown() {
+        assert_eq!(sniff(b"This is where your secret message will be!"), "unknown");
+        assert_eq!(sniff(&[]), "unknown");
+        assert_eq!(sniff(b"PK"), "unknown");
+    }
+}
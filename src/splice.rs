@@ -0,0 +1,126 @@
+use crate::chunk::Chunk;
+use crate::error::ReasonError;
+use crate::png::Png;
+use crate::Result;
+
+use std::io::{self, Read, Write};
+
+/// Prefix of the errors found while splicing a chunk into a PNG stream
+const ERROR_PREFIX: &str = "Can't splice chunk";
+
+/// Copies the PNG from the source to the output chunk by chunk, inserting the chunk before IEND
+/// (or at the end if there is no IEND). Only chunk headers are buffered, the source data is streamed.
+pub fn splice_chunk<R: Read, W: Write>(mut source: R, mut output: W, chunk: &Chunk) -> Result<()> {
+    let mut header = [0u8; 8];
+    source.read_exact(&mut header)?;
+    if header != Png::STANDARD_HEADER {
+        return Err(ReasonError::boxed(ERROR_PREFIX, "Invalid PNG header found".to_string()));
+    }
+    output.write_all(&header)?;
+
+    let mut inserted = false;
+    loop {
+        // Length + type of the next chunk
+        let mut chunk_header = [0u8; 8];
+        match read_header(&mut source, &mut chunk_header)? {
+            0 => break,
+            8 => {},
+            read => return Err(ReasonError::boxed(ERROR_PREFIX, format!("Truncated chunk header ({read} bytes)"))),
+        }
+
+        if &chunk_header[4..8] == b"IEND" && !inserted {
+            output.write_all(&chunk.as_bytes())?;
+            inserted = true;
+        }
+        output.write_all(&chunk_header)?;
+
+        // Data + CRC
+        let remaining = u32::from_be_bytes(chunk_header[0..4].try_into()?) as u64 + 4;
+        let copied = io::copy(&mut (&mut source).take(remaining), &mut output)?;
+        if copied != remaining {
+            return Err(ReasonError::boxed(ERROR_PREFIX, format!("Truncated chunk (missing {} bytes)", remaining - copied)));
+        }
+    }
+
+    if !inserted {
+        output.write_all(&chunk.as_bytes())?;
+    }
+    output.flush()?;
+    Ok(())
+}
+
+/// Fills the buffer from the source, returning how many bytes were read before the end of the source
+pub(crate) fn read_header<R: Read>(source: &mut R, buffer: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+    while read < buffer.len() {
+        match source.read(&mut buffer[read..]) {
+            Ok(0) => break,
+            Ok(count) => read += count,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunk_type::ChunkType;
+    use std::io::Cursor;
+    use std::str::FromStr;
+
+    fn chunk(chunk_type: &str, data: &[u8]) -> Chunk {
+        Chunk::new(ChunkType::from_str(chunk_type).unwrap(), data.to_vec())
+    }
+
+    fn testing_png() -> Png {
+        Png::from_chunks(vec![
+            chunk("IHDR", &[0; 13]),
+            chunk("IDAT", b"image data"),
+            chunk("IEND", &[]),
+        ])
+    }
+
+    #[test]
+    fn test_splice_before_iend() {
+        let mut output = vec![];
+        let message = chunk("ruSt", b"message");
+        splice_chunk(Cursor::new(testing_png().as_bytes()), &mut output, &message).unwrap();
+
+        let png = Png::try_from(output.as_slice()).unwrap();
+        let types: Vec<String> = png.chunks().iter().map(|chunk| chunk.chunk_type().to_string()).collect();
+        assert_eq!(types, ["IHDR", "IDAT", "ruSt", "IEND"]);
+    }
+
+    #[test]
+    fn test_splice_without_iend() {
+        let png = Png::from_chunks(vec![chunk("IHDR", &[0; 13])]);
+        let mut output = vec![];
+        splice_chunk(Cursor::new(png.as_bytes()), &mut output, &chunk("ruSt", b"message")).unwrap();
+
+        let png = Png::try_from(output.as_slice()).unwrap();
+        assert_eq!(&png.chunks()[1].chunk_type().to_string(), "ruSt");
+    }
+
+    #[test]
+    fn test_splice_large_message() {
+        let message: Vec<u8> = (0..=255).cycle().take(4 * 1024 * 1024).collect();
+        let mut output = vec![];
+        splice_chunk(Cursor::new(testing_png().as_bytes()), &mut output, &chunk("ruSt", &message)).unwrap();
+
+        let png = Png::try_from(output.as_slice()).unwrap();
+        assert_eq!(png.chunks()[2].data(), message.as_slice());
+    }
+
+    #[test]
+    fn test_splice_invalid_source() {
+        let mut bytes = testing_png().as_bytes();
+        bytes.truncate(bytes.len() - 2);
+        assert!(splice_chunk(Cursor::new(bytes), vec![], &chunk("ruSt", b"message")).is_err());
+
+        let mut bytes = testing_png().as_bytes();
+        bytes[0] = 0;
+        assert!(splice_chunk(Cursor::new(bytes), vec![], &chunk("ruSt", b"message")).is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use crate::codec::{zlib_compress, zlib_decompress};
+use crate::error::ReasonError;
+use crate::Result;
+
+/// Maximum length of a tEXt keyword allowed by the PNG spec
+pub const MAX_KEYWORD_LENGTH: usize = 79;
+
+/// Compression method byte of zTXt chunks, zlib being the only method defined by the PNG spec
+const ZLIB_METHOD: u8 = 0;
+/// Prefix of the errors of malformed text chunks
+const ERROR_PREFIX: &str = "Bad text chunk";
+
+/// Encodes a keyword and its text as tEXt chunk data, both in Latin-1 and separated by a null byte.
+/// The keyword must be 1-79 printable Latin-1 characters without leading or trailing spaces
+pub fn encode_text(keyword: &str, text: &str) -> Result<Vec<u8>> {
+    check_keyword(keyword)?;
+    check_text(text)?;
+
+    Ok(keyword.chars()
+        .chain(std::iter::once('\0'))
+        .chain(text.chars())
+        .map(|c| c as u8)
+        .collect())
+}
+
+/// Splits tEXt chunk data into its keyword and text on the first null byte, decoding both from Latin-1
+pub fn decode_text(data: &[u8]) -> Result<(String, String)> {
+    let (keyword, text) = split_keyword(data)?;
+    Ok((keyword, latin1(text)))
+}
+
+/// Encodes a keyword and its text as zTXt chunk data: the keyword and a null byte like tEXt,
+/// then the compression method byte (0) and the zlib-compressed Latin-1 text
+pub fn encode_ztxt(keyword: &str, text: &str) -> Result<Vec<u8>> {
+    check_keyword(keyword)?;
+    check_text(text)?;
+
+    let mut data: Vec<u8> = keyword.chars().map(|c| c as u8).collect();
+    data.extend_from_slice(&[0, ZLIB_METHOD]);
+    data.extend(zlib_compress(&text.chars().map(|c| c as u8).collect::<Vec<u8>>())?);
+    Ok(data)
+}
+
+/// Splits zTXt chunk data into its keyword and decompressed text, decoding both from Latin-1
+pub fn decode_ztxt(data: &[u8]) -> Result<(String, String)> {
+    let (keyword, rest) = split_keyword(data)?;
+    let compressed = match rest.split_first() {
+        Some((&ZLIB_METHOD, compressed)) => compressed,
+        Some((method, _)) => return Err(ReasonError::boxed(ERROR_PREFIX, format!("unknown compression method {method}"))),
+        None => return Err(ReasonError::boxed(ERROR_PREFIX, "missing the compression method".to_string())),
+    };
+
+    let text = zlib_decompress(compressed).map_err(|reason| ReasonError::boxed(ERROR_PREFIX, reason))?;
+    Ok((keyword, latin1(&text)))
+}
+
+/// Fails unless the keyword is 1-79 printable Latin-1 characters without leading or trailing spaces
+pub fn check_keyword(keyword: &str) -> Result<()> {
+    let length = keyword.chars().count();
+    if length == 0 || length > MAX_KEYWORD_LENGTH {
+        return Err(ReasonError::boxed(ERROR_PREFIX, format!("keyword must be 1-{MAX_KEYWORD_LENGTH} characters long (got {length})")));
+    }
+    if keyword.starts_with(' ') || keyword.ends_with(' ') {
+        return Err(ReasonError::boxed(ERROR_PREFIX, format!("keyword \"{keyword}\" can't start or end with a space")));
+    }
+    if !keyword.chars().all(|c| matches!(c as u32, 32..=126 | 161..=255)) {
+        return Err(ReasonError::boxed(ERROR_PREFIX, format!("keyword \"{keyword}\" must only contain printable Latin-1 characters")));
+    }
+    Ok(())
+}
+
+fn check_text(text: &str) -> Result<()> {
+    if !text.chars().all(|c| matches!(c as u32, 1..=255)) {
+        return Err(ReasonError::boxed(ERROR_PREFIX, "text must only contain non-null Latin-1 characters".to_string()));
+    }
+    Ok(())
+}
+
+/// Splits the data on its first null byte, returning the Latin-1 keyword before it and the bytes after it
+fn split_keyword(data: &[u8]) -> Result<(String, &[u8])> {
+    let separator = data
+        .iter()
+        .position(|&byte| byte == 0)
+        .ok_or_else(|| ReasonError::boxed(ERROR_PREFIX, "missing the null separator".to_string()))?;
+    if separator == 0 || separator > MAX_KEYWORD_LENGTH {
+        return Err(ReasonError::boxed(ERROR_PREFIX, format!("keyword must be 1-{MAX_KEYWORD_LENGTH} characters long (got {separator})")));
+    }
+    Ok((latin1(&data[..separator]), &data[separator + 1..]))
+}
+
+fn latin1(bytes: &[u8]) -> String {
+    bytes.iter().map(|&byte| byte as char).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let data = encode_text("Author", "Jane Doe").unwrap();
+        assert_eq!(data, b"Author\0Jane Doe");
+        assert_eq!(decode_text(&data).unwrap(), ("Author".to_string(), "Jane Doe".to_string()));
+    }
+
+    #[test]
+    fn test_round_trip_latin1() {
+        let data = encode_text("Légende", "café ©").unwrap();
+        assert_eq!(data.len(), "Légende".chars().count() + 1 + "café ©".chars().count());
+        assert_eq!(decode_text(&data).unwrap(), ("Légende".to_string(), "café ©".to_string()));
+    }
+
+    #[test]
+    fn test_keyword_length() {
+        assert!(encode_text(&"k".repeat(MAX_KEYWORD_LENGTH), "text").is_ok());
+        assert!(encode_text(&"k".repeat(80), "text").is_err());
+        assert!(encode_text("", "text").is_err());
+
+        let mut data = vec![b'k'; 80];
+        data.extend_from_slice(b"\0text");
+        assert!(decode_text(&data).is_err());
+    }
+
+    #[test]
+    fn test_invalid_text() {
+        assert!(encode_text(" Author", "text").is_err());
+        assert!(encode_text("Auth\u{7f}or", "text").is_err());
+        assert!(encode_text("Author", "emoji 🦀").is_err());
+        assert!(encode_text("Author", "null \0").is_err());
+
+        assert!(decode_text(b"Author").is_err());
+        assert!(decode_text(b"\0text").is_err());
+    }
+
+    #[test]
+    fn test_ztxt_round_trip() {
+        let text = "A longer description, ".repeat(20) + "café ©";
+        let data = encode_ztxt("Description", &text).unwrap();
+        assert!(data.starts_with(b"Description\0\0"));
+        assert!(data.len() < text.len());
+        assert_eq!(decode_ztxt(&data).unwrap(), ("Description".to_string(), text));
+
+        let data = encode_ztxt("Comment", "").unwrap();
+        assert_eq!(decode_ztxt(&data).unwrap(), ("Comment".to_string(), String::new()));
+    }
+
+    #[test]
+    fn test_ztxt_unknown_compression_method() {
+        let mut data = encode_ztxt("Comment", "text").unwrap();
+        data["Comment".len() + 1] = 1;
+        assert_eq!(decode_ztxt(&data).unwrap_err().to_string(), "Bad text chunk: unknown compression method 1");
+    }
+
+    #[test]
+    fn test_invalid_ztxt() {
+        assert!(encode_ztxt("", "text").is_err());
+        assert!(encode_ztxt("Comment", "emoji 🦀").is_err());
+        assert!(decode_ztxt(b"Comment\0").is_err());
+        assert!(decode_ztxt(b"Comment\0\0not zlib").is_err());
+        assert!(decode_ztxt(b"Comment").is_err());
+    }
+}
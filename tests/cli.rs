@@ -0,0 +1,214 @@
+use std::fs;
+use std::process::Command;
+
+use pngme::{Chunk, ChunkType, Png};
+
+/// Writes a PNG with a single "ruSt" chunk to a temporary file
+fn png_file() -> tempfile::TempPath {
+    let path = tempfile::NamedTempFile::new().unwrap().into_temp_path();
+    let png = Png::from_chunks(vec![Chunk::new(ChunkType::new(*b"ruSt").unwrap(), b"hidden".to_vec())]);
+    fs::write(&path, png.as_bytes()).unwrap();
+    path
+}
+
+fn pngme(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_PNGme")).args(args).output().unwrap()
+}
+
+#[test]
+fn test_decode_exit_status() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+
+    let found = pngme(&["decode", filepath, "ruSt"]);
+    assert!(found.status.success());
+    assert!(String::from_utf8_lossy(&found.stdout).contains("\"hidden\""));
+
+    let missing = pngme(&["decode", filepath, "noNe"]);
+    assert_eq!(missing.status.code(), Some(1));
+    assert!(missing.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&missing.stderr).contains("Error: No chunk found with type \"noNe\""));
+}
+
+#[test]
+fn test_quiet_only_prints_results() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+
+    let encoded = pngme(&["-q", "encode", filepath, "ruSt", "shown"]);
+    assert!(encoded.status.success());
+    assert!(encoded.stdout.is_empty());
+    assert!(encoded.stderr.is_empty());
+
+    let decoded = pngme(&["decode", filepath, "ruSt", "--quiet"]);
+    assert!(String::from_utf8_lossy(&decoded.stdout).contains("\"shown\""));
+    assert!(decoded.stderr.is_empty());
+
+    let removed = pngme(&["-q", "remove", filepath, "ruSt"]);
+    assert!(removed.status.success());
+    assert!(removed.stdout.is_empty());
+    assert!(removed.stderr.is_empty());
+
+    let removed = pngme(&["remove", filepath, "ruSt"]);
+    assert!(removed.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&removed.stderr).contains("Removed hidden message"));
+}
+
+#[test]
+fn test_verbose_prints_debug_info() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+
+    let decoded = pngme(&["-v", "decode", filepath, "ruSt"]);
+    assert!(String::from_utf8_lossy(&decoded.stdout).contains("\"hidden\""));
+    assert!(String::from_utf8_lossy(&decoded.stderr).contains("Read 1 chunk(s)"));
+}
+
+#[test]
+fn test_meta_prints_text_chunks() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+    let mut png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+    png.append_chunk(Chunk::text("Author", "Jane Doe").unwrap());
+    png.append_chunk(Chunk::text("Title", "Dice").unwrap());
+    fs::write(&path, png.as_bytes()).unwrap();
+
+    let meta = pngme(&["meta", filepath]);
+    assert!(meta.status.success());
+    assert_eq!(String::from_utf8_lossy(&meta.stdout), "Author: Jane Doe\nTitle: Dice\n");
+}
+
+#[test]
+fn test_errors_print_their_message() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+    let mut bytes = fs::read(&path).unwrap();
+    let last = bytes.len() - 1;
+    bytes[last] ^= 1;
+    fs::write(&path, bytes).unwrap();
+
+    let printed = pngme(&["print", filepath]);
+    assert_eq!(printed.status.code(), Some(1));
+    let stderr = String::from_utf8_lossy(&printed.stderr);
+    assert!(stderr.starts_with("Error: Bad chunk at offset 8: CRC mismatch"));
+    assert!(!stderr.contains("ChunkDecodingError"));
+}
+
+#[test]
+fn test_decode_binary_messages_as_base64() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+    assert!(pngme(&["encode", filepath, "biNy", "//4AgA==", "--base64"]).status.success());
+    assert!(pngme(&["encode", filepath, "chAn", "//4AgA==", "--base64", "--chain"]).status.success());
+
+    let decoded = pngme(&["decode", filepath, "biNy"]);
+    assert!(String::from_utf8_lossy(&decoded.stdout).contains("Found hidden message 0: (base64) //4AgA== in chunk"));
+    let decoded = pngme(&["decode", filepath, "biNy", "--max-bytes", "2"]);
+    assert!(String::from_utf8_lossy(&decoded.stdout).contains(": (base64) //4= (truncated to 2 of 4 bytes) in chunk"));
+
+    let joined = pngme(&["decode", filepath, "biNy", "--join", "--max-bytes", "2"]);
+    assert!(String::from_utf8_lossy(&joined.stdout).contains(": (base64) //4= (truncated to 2 of 4 bytes) in 1 chunk(s)"));
+
+    let chained = pngme(&["decode", filepath, "chAn", "--verify-chain", "--max-bytes", "2"]);
+    assert!(chained.status.success());
+    assert!(String::from_utf8_lossy(&chained.stdout).contains(": (base64) //4= (truncated to 2 of 4 bytes) in chunk"));
+}
+
+#[test]
+fn test_print_sniff_uses_file_indices() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+    assert!(pngme(&["encode", filepath, "teSt", "second"]).status.success());
+
+    let printed = pngme(&["print", filepath, "--type", "teSt", "--sniff"]);
+    let stdout = String::from_utf8_lossy(&printed.stdout);
+    assert!(stdout.contains("Chunk 1 (teSt) data:"));
+    assert!(!stdout.contains("Chunk 0"));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_encode_stdin_keeps_permissions() {
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+    use std::process::Stdio;
+
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_PNGme"))
+        .args(["encode", filepath, "stIn", "--stdin"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"from stdin").unwrap();
+    assert!(child.wait().unwrap().success());
+
+    assert_eq!(fs::metadata(&path).unwrap().permissions().mode() & 0o777, 0o644);
+    let png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+    assert_eq!(png.chunk_by_type(ChunkType::new(*b"stIn").unwrap()).unwrap().data(), b"from stdin");
+}
+
+#[test]
+fn test_deterministic_runs_are_identical() {
+    let time = Chunk::new(ChunkType::new(*b"tIME").unwrap(), vec![7, 234, 10, 14, 12, 30, 5]);
+    let outputs: Vec<Vec<u8>> = (0..2)
+        .map(|_| {
+            let path = png_file();
+            let filepath = path.to_str().unwrap();
+            let mut png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+            png.append_chunk(time.clone());
+            fs::write(&path, png.as_bytes()).unwrap();
+
+            let encoded = pngme(&["--deterministic", "encode", filepath, "seCr", "note", "--password", "hunter2"]);
+            assert!(encoded.status.success());
+            fs::read(&path).unwrap()
+        })
+        .collect();
+
+    assert_eq!(outputs[0], outputs[1]);
+    let png = Png::try_from(outputs[0].as_slice()).unwrap();
+    assert_eq!(png.chunk_by_type(ChunkType::new(*b"tIME").unwrap()).unwrap().data(), time.data());
+}
+
+#[test]
+fn test_decode_max_bytes_reports_truncation() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+
+    let hex = pngme(&["decode", filepath, "ruSt", "--hex", "--max-bytes", "2"]);
+    assert!(String::from_utf8_lossy(&hex.stdout).contains("(hex): 6869 (truncated to 2 of 6 bytes) in chunk"));
+    let joined = pngme(&["decode", filepath, "ruSt", "--hex", "--join", "--max-bytes", "2"]);
+    assert!(String::from_utf8_lossy(&joined.stdout).contains("(hex): 6869 (truncated to 2 of 6 bytes) in 1 chunk(s)"));
+
+    for format in ["json", "rust", "c"] {
+        let decoded = pngme(&["decode", filepath, "ruSt", "--format", format, "--max-bytes", "2"]);
+        assert!(decoded.status.success());
+        assert_eq!(String::from_utf8_lossy(&decoded.stderr), "Warning: Message truncated to 2 of 6 bytes\n");
+    }
+    let complete = pngme(&["decode", filepath, "ruSt", "--format", "json", "--max-bytes", "6"]);
+    assert!(complete.stderr.is_empty());
+}
+
+#[test]
+fn test_quiet_keeps_command_results() {
+    let path = png_file();
+    let filepath = path.to_str().unwrap();
+    let mut png = Png::try_from(fs::read(&path).unwrap().as_slice()).unwrap();
+    png.append_chunk(Chunk::new(ChunkType::new(*b"IDAT").unwrap(), b"first".to_vec()));
+    png.append_chunk(Chunk::new(ChunkType::new(*b"IDAT").unwrap(), b"second".to_vec()));
+    png.append_chunk(Chunk::new(ChunkType::new(*b"ruSt").unwrap(), b"hidden".to_vec()));
+    fs::write(&path, png.as_bytes()).unwrap();
+
+    let optimized = pngme(&["-q", "--dry-run", "optimize", filepath]);
+    assert!(String::from_utf8_lossy(&optimized.stdout).contains("Would remove 1 IDAT chunk(s) by merging, would save 12 bytes"));
+    let optimized = pngme(&["-q", "optimize", filepath]);
+    assert!(String::from_utf8_lossy(&optimized.stdout).starts_with("Removed 1 IDAT chunk(s) by merging, saved 12 bytes"));
+
+    let deduped = pngme(&["-q", "dedup", filepath]);
+    assert_eq!(String::from_utf8_lossy(&deduped.stdout), "Removed 1 duplicate chunk(s)\n");
+
+    let fixed = pngme(&["-q", "fix", filepath]);
+    assert_eq!(String::from_utf8_lossy(&fixed.stdout), format!("Corrected 0 CRC(s) in \"{filepath}\"\n"));
+}